@@ -0,0 +1,595 @@
+//! Page cache layout
+//!
+//! Downloaded pages are stored under file names derived from the reference
+//! names with [`file_name_for`], which only uses characters that are valid on
+//! every common file system (including FAT formatted USB sticks) and need no
+//! quoting in a shell:
+//!
+//! - `::` becomes `.`
+//! - ASCII letters, digits, `_` and `-` are kept
+//! - Everything else, including `.` and `%`, becomes `%XX` for each UTF-8 byte
+//!
+//! For example, `std::vector<T,Allocator>::operator[]` is stored as
+//! `std.vector%3CT%2CAllocator%3E.operator%5B%5D.html`.
+//!
+//! A `manifest.json` file in the cache directory maps every reference name to
+//! its file, together with where and when the page was fetched, the HTTP
+//! validators (`ETag` and `Last-Modified`) needed for conditional refreshes,
+//! the SHA-256 hash of the stored file and the version of the processing
+//! applied to it. Directories written by older versions, which stored pages as
+//! `{name}.html`, are migrated when they are opened.
+//!
+//! Stylesheets, images and fonts used by the pages are stored once in the
+//! `assets` subdirectory, named after the SHA-256 hash of their content, and
+//! the manifest maps every asset URL to its file.
+//!
+//! The pages as served, before any processing, are kept under the same file
+//! names in the `raw` subdirectory, so that they can be processed again
+//! without downloading them (see `ref reprocess`).
+
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{errors::AppError, html::PROCESSING_VERSION};
+
+/// Name of the manifest file in a cache directory
+pub const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Version of the manifest format
+///
+/// Version 2 added the fetch metadata of [`PageMetadata`], version 3 the
+/// assets, version 4 the raw copies and archive paths of pages and version 5
+/// whether the assets of pages are linked; older manifests are read with empty
+/// metadata and no assets.
+pub const MANIFEST_VERSION: u32 = 5;
+
+/// Name of the subdirectory of a cache directory holding the assets
+pub const ASSETS_DIR_NAME: &str = "assets";
+
+/// Name of the subdirectory of a cache directory holding the pages as served
+pub const RAW_DIR_NAME: &str = "raw";
+
+/// Name of the subdirectory of a cache directory holding rejected pages
+///
+/// Quarantined pages are not listed in the manifest and never printed.
+pub const QUARANTINE_DIR_NAME: &str = "quarantine";
+
+/// Device names that cannot be used as file names on Windows, even with an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The manifest of a cache directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Version of the manifest format
+    pub version: u32,
+    /// Cached pages keyed by reference name
+    pub pages: BTreeMap<String, ManifestEntry>,
+    /// File names of the cached assets, relative to the assets directory,
+    /// keyed by URL
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub assets: BTreeMap<String, String>,
+}
+
+/// A page in the manifest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// File name of the page, relative to the cache directory
+    pub file: String,
+    /// Where and when the page was fetched
+    #[serde(flatten)]
+    pub metadata: PageMetadata,
+}
+
+/// Fetch metadata of a cached page
+///
+/// Every field is missing for pages adopted from older cache directories.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PageMetadata {
+    /// The URL that was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// The URL the page was served from, after redirects
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub final_url: Option<String>,
+    /// When the page was last fetched, in seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetched_at: Option<u64>,
+    /// The `ETag` header of the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    /// The `Last-Modified` header of the response
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// SHA-256 hash of the stored file, in hexadecimal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Version of the processing applied to the stored file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub processing_version: Option<u32>,
+    /// Whether the links of the stored file point to the cached assets
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub assets_linked: bool,
+    /// SHA-256 hash of the page as served, kept in [`RAW_DIR_NAME`], in hexadecimal
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_sha256: Option<String>,
+    /// The path of the page in the offline archive, if it was imported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<String>,
+}
+
+impl PageMetadata {
+    /// Whether the stored file was produced by the current processing
+    ///
+    /// Pages processed by an older [`PROCESSING_VERSION`], or with assets
+    /// linked differently, are outdated.
+    ///
+    /// # Arguments
+    ///
+    /// * `assets` - Whether the links should point to the cached assets
+    pub fn is_current(&self, assets: bool) -> bool {
+        self.processing_version == Some(PROCESSING_VERSION) && self.assets_linked == assets
+    }
+}
+
+/// A directory of downloaded pages together with its manifest
+#[derive(Debug, Clone)]
+pub struct PageCache {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl PageCache {
+    /// Open a cache directory
+    ///
+    /// The manifest is loaded and reconciled with the directory: entries whose
+    /// file is missing are dropped, raw copies that are missing are forgotten,
+    /// and HTML files not in the manifest are added. Files named after the raw
+    /// reference name (`std::vector.html`) are renamed to their escaped names.
+    /// The manifest is saved if anything changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The cache directory, which must exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read, the manifest is
+    /// invalid, or a file cannot be renamed.
+    pub fn open(dir: &Path) -> Result<PageCache, AppError> {
+        Self::read(dir, true)
+    }
+
+    /// Load a cache directory without changing it
+    ///
+    /// The manifest is reconciled with the directory like in
+    /// [`PageCache::open`], but only in memory: files named after the raw
+    /// reference name are listed under their old names instead of being
+    /// renamed, and the manifest is not saved. Use it for reports and listings
+    /// that must not touch the cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The cache directory, which must exist
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read or the manifest is
+    /// invalid.
+    pub fn load(dir: &Path) -> Result<PageCache, AppError> {
+        Self::read(dir, false)
+    }
+
+    /// Read and reconcile a cache directory, migrating and saving it if `write`
+    fn read(dir: &Path, write: bool) -> Result<PageCache, AppError> {
+        let manifest_path = dir.join(MANIFEST_FILE_NAME);
+        let mut manifest = if manifest_path.is_file() {
+            serde_json::from_str(&fs::read_to_string(&manifest_path)?)?
+        } else {
+            Manifest {
+                version: MANIFEST_VERSION,
+                pages: BTreeMap::new(),
+                assets: BTreeMap::new(),
+            }
+        };
+        let original = manifest.clone();
+        manifest.version = MANIFEST_VERSION;
+
+        manifest
+            .pages
+            .retain(|_, entry| dir.join(&entry.file).is_file());
+        manifest
+            .assets
+            .retain(|_, file| dir.join(ASSETS_DIR_NAME).join(file).is_file());
+        for entry in manifest.pages.values_mut() {
+            if !dir.join(RAW_DIR_NAME).join(&entry.file).is_file() {
+                entry.metadata.raw_sha256 = None;
+            }
+        }
+
+        let listed: Vec<&String> = manifest.pages.values().map(|entry| &entry.file).collect();
+        let mut unlisted = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "html") {
+                continue;
+            }
+            if let Some(file) = path.file_name().and_then(|file| file.to_str())
+                && !listed.iter().any(|listed| *listed == file)
+            {
+                unlisted.push(file.to_string());
+            }
+        }
+        unlisted.sort();
+
+        for file in unlisted {
+            let stem = file.trim_end_matches(".html");
+            let (name, file) = match name_from_file_stem(stem) {
+                Some(name) => (name, file.clone()),
+                None => {
+                    // A page stored under its raw name by an older version
+                    let escaped = file_name_for(stem);
+                    if dir.join(&escaped).exists() {
+                        debug!("Skipping {:?}, which is also cached as {:?}", file, escaped);
+                        continue;
+                    }
+                    if !write {
+                        manifest
+                            .pages
+                            .entry(stem.to_string())
+                            .or_insert(ManifestEntry {
+                                file: file.clone(),
+                                metadata: PageMetadata::default(),
+                            });
+                        continue;
+                    }
+                    info!("Migrating {:?} to {:?}", file, escaped);
+                    fs::rename(dir.join(&file), dir.join(&escaped))?;
+                    (stem.to_string(), escaped)
+                }
+            };
+            manifest.pages.entry(name).or_insert(ManifestEntry {
+                file,
+                metadata: PageMetadata::default(),
+            });
+        }
+
+        let cache = PageCache {
+            dir: dir.to_path_buf(),
+            manifest,
+        };
+        if write && (cache.manifest != original || !manifest_path.is_file()) {
+            cache.save()?;
+        }
+        Ok(cache)
+    }
+
+    /// The cache directory
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The manifest
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+
+    /// Whether a page is cached
+    pub fn contains(&self, name: &str) -> bool {
+        self.manifest.pages.contains_key(name)
+    }
+
+    /// The manifest entry of a cached page
+    pub fn entry(&self, name: &str) -> Option<&ManifestEntry> {
+        self.manifest.pages.get(name)
+    }
+
+    /// The path a page is (or will be) stored at
+    pub fn page_path(&self, name: &str) -> PathBuf {
+        match self.manifest.pages.get(name) {
+            Some(entry) => self.dir.join(&entry.file),
+            None => self.dir.join(file_name_for(name)),
+        }
+    }
+
+    /// The path the page as served is (or will be) kept at
+    pub fn raw_path(&self, name: &str) -> PathBuf {
+        let file = match self.manifest.pages.get(name) {
+            Some(entry) => entry.file.clone(),
+            None => file_name_for(name),
+        };
+        self.dir.join(RAW_DIR_NAME).join(file)
+    }
+
+    /// Write the page as served to [`PageCache::raw_path`] unless it is unchanged
+    ///
+    /// # Returns
+    ///
+    /// The SHA-256 hash of the page as served, to record as
+    /// [`PageMetadata::raw_sha256`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn write_raw(&self, name: &str, raw: &str) -> Result<String, AppError> {
+        let sha256 = sha256_hex(raw.as_bytes());
+        let path = self.raw_path(name);
+        let unchanged = path.is_file()
+            && self
+                .entry(name)
+                .is_some_and(|entry| entry.metadata.raw_sha256.as_ref() == Some(&sha256));
+        if !unchanged {
+            fs::create_dir_all(self.dir.join(RAW_DIR_NAME))?;
+            fs::write(&path, raw)?;
+        }
+        Ok(sha256)
+    }
+
+    /// Record that a page has been written to [`PageCache::page_path`]
+    ///
+    /// The metadata of a page that is already cached is replaced.
+    pub fn insert(&mut self, name: &str, metadata: PageMetadata) {
+        let file = match self.manifest.pages.get(name) {
+            Some(entry) => entry.file.clone(),
+            None => file_name_for(name),
+        };
+        self.manifest
+            .pages
+            .insert(name.to_string(), ManifestEntry { file, metadata });
+    }
+
+    /// Forget a cached page
+    ///
+    /// The files of the page are left in place; the caller removes or moves
+    /// them before saving the manifest.
+    ///
+    /// # Returns
+    ///
+    /// The manifest entry of the page, or `None` if it was not cached.
+    pub fn remove(&mut self, name: &str) -> Option<ManifestEntry> {
+        self.manifest.pages.remove(name)
+    }
+
+    /// The directory assets are stored in
+    pub fn assets_dir(&self) -> PathBuf {
+        self.dir.join(ASSETS_DIR_NAME)
+    }
+
+    /// The file name of a cached asset, relative to [`PageCache::assets_dir`]
+    pub fn asset(&self, url: &str) -> Option<&str> {
+        self.manifest.assets.get(url).map(String::as_str)
+    }
+
+    /// Record that an asset has been written to [`PageCache::assets_dir`]
+    pub fn insert_asset(&mut self, url: &str, file: &str) {
+        self.manifest
+            .assets
+            .insert(url.to_string(), file.to_string());
+    }
+
+    /// Write the manifest to the cache directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the manifest cannot be written.
+    pub fn save(&self) -> Result<(), AppError> {
+        let content = serde_json::to_string_pretty(&self.manifest)?;
+        fs::write(self.dir.join(MANIFEST_FILE_NAME), content)?;
+        Ok(())
+    }
+}
+
+/// The file name a page is stored under
+///
+/// # Examples
+///
+/// ```
+/// use algcmp::cache::file_name_for;
+///
+/// assert_eq!(file_name_for("std::ranges::sort"), "std.ranges.sort.html");
+/// assert_eq!(
+///     file_name_for("std::vector<T,Allocator>::operator[]"),
+///     "std.vector%3CT%2CAllocator%3E.operator%5B%5D.html"
+/// );
+/// ```
+pub fn file_name_for(name: &str) -> String {
+    let mut stem = String::new();
+    let mut rest = name;
+    while let Some(c) = rest.chars().next() {
+        if let Some(tail) = rest.strip_prefix("::") {
+            stem.push('.');
+            rest = tail;
+            continue;
+        }
+        if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+            stem.push(c);
+        } else {
+            push_escaped(&mut stem, c);
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    // Escape the first character of reserved device names such as `CON`
+    let device = stem.split('.').next().unwrap_or_default();
+    if RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(device))
+    {
+        let first = stem.remove(0);
+        let mut escaped = String::new();
+        push_escaped(&mut escaped, first);
+        stem.insert_str(0, &escaped);
+    }
+
+    format!("{}.html", stem)
+}
+
+/// The SHA-256 hash of some content, in hexadecimal
+///
+/// # Examples
+///
+/// ```
+/// use algcmp::cache::sha256_hex;
+///
+/// assert_eq!(
+///     sha256_hex(b"abc"),
+///     "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+/// );
+/// ```
+pub fn sha256_hex(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Append the `%XX` escapes of a character
+fn push_escaped(stem: &mut String, c: char) {
+    let mut buffer = [0; 4];
+    for byte in c.encode_utf8(&mut buffer).bytes() {
+        stem.push_str(&format!("%{:02X}", byte));
+    }
+}
+
+/// The reference name of an escaped file stem
+///
+/// # Returns
+///
+/// The name, or `None` if `stem` is not a valid escaped name.
+pub fn name_from_file_stem(stem: &str) -> Option<String> {
+    let mut bytes = Vec::new();
+    let mut rest = stem.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match byte {
+            b'.' => bytes.extend_from_slice(b"::"),
+            b'%' => {
+                let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &tail[2..];
+                continue;
+            }
+            b if b.is_ascii_alphanumeric() || b == b'_' || b == b'-' => bytes.push(b),
+            _ => return None,
+        }
+        rest = tail;
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_names_round_trip() {
+        for name in [
+            "std::vector<T,Allocator>::operator[]",
+            "std::map<Key,std::less<Key>>::operator<=>",
+            "std::ranges::views::iota",
+            "std::operator\"\"s",
+            "std::size_t",
+            "con",
+            "去重",
+        ] {
+            let file = file_name_for(name);
+            let stem = file.strip_suffix(".html").unwrap();
+            assert!(
+                stem.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_-.%".contains(c)),
+                "{}",
+                file
+            );
+            assert_eq!(name_from_file_stem(stem).as_deref(), Some(name));
+        }
+        assert_eq!(file_name_for("con"), "%63on.html");
+        assert_eq!(name_from_file_stem("std::vector"), None);
+    }
+
+    #[test]
+    fn test_open_migrates_legacy_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("std::stack<T,Container>::stack.html"), "stack").unwrap();
+        fs::write(dir.join("std.vector.html"), "vector").unwrap();
+        fs::write(dir.join("notes.txt"), "").unwrap();
+
+        let cache = PageCache::open(dir).unwrap();
+        let path = cache.page_path("std::stack<T,Container>::stack");
+        assert_eq!(path, dir.join("std.stack%3CT%2CContainer%3E.stack.html"));
+        assert_eq!(fs::read_to_string(path).unwrap(), "stack");
+        assert!(cache.contains("std::vector"));
+        assert!(!dir.join("std::stack<T,Container>::stack.html").exists());
+
+        // The manifest is saved and reused
+        fs::remove_file(dir.join("std.vector.html")).unwrap();
+        let cache = PageCache::open(dir).unwrap();
+        assert!(!cache.contains("std::vector"));
+        let manifest: Manifest =
+            serde_json::from_str(&fs::read_to_string(dir.join(MANIFEST_FILE_NAME)).unwrap())
+                .unwrap();
+        assert_eq!(manifest.version, MANIFEST_VERSION);
+        assert_eq!(
+            manifest.pages.keys().collect::<Vec<_>>(),
+            vec!["std::stack<T,Container>::stack"]
+        );
+    }
+
+    #[test]
+    fn test_load_leaves_the_directory_unchanged() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("std::stack<T,Container>::stack.html"), "stack").unwrap();
+        fs::write(dir.join("std.vector.html"), "vector").unwrap();
+
+        let cache = PageCache::load(dir).unwrap();
+        let path = cache.page_path("std::stack<T,Container>::stack");
+        assert_eq!(path, dir.join("std::stack<T,Container>::stack.html"));
+        assert_eq!(fs::read_to_string(path).unwrap(), "stack");
+        assert!(cache.contains("std::vector"));
+        assert!(!dir.join(MANIFEST_FILE_NAME).exists());
+        assert!(!dir.join("std.stack%3CT%2CContainer%3E.stack.html").exists());
+    }
+
+    #[test]
+    fn test_open_reads_version_1_manifest() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        fs::write(dir.join("std.sort.html"), "sort").unwrap();
+        fs::write(
+            dir.join(MANIFEST_FILE_NAME),
+            r#"{"version": 1, "pages": {"std::sort": {"file": "std.sort.html"}}}"#,
+        )
+        .unwrap();
+
+        let mut cache = PageCache::open(dir).unwrap();
+        assert_eq!(cache.manifest().version, MANIFEST_VERSION);
+        assert_eq!(
+            cache.entry("std::sort").unwrap().metadata,
+            PageMetadata::default()
+        );
+
+        cache.insert(
+            "std::sort",
+            PageMetadata {
+                etag: Some("\"abc\"".to_string()),
+                sha256: Some(sha256_hex(b"sort")),
+                ..PageMetadata::default()
+            },
+        );
+        cache.save().unwrap();
+        let cache = PageCache::open(dir).unwrap();
+        let entry = cache.entry("std::sort").unwrap();
+        assert_eq!(entry.file, "std.sort.html");
+        assert_eq!(entry.metadata.etag.as_deref(), Some("\"abc\""));
+        assert_eq!(entry.metadata.url, None);
+    }
+}
@@ -5,7 +5,43 @@
 //! # Available Commands
 //!
 //! - [`download`] - Download C++ reference pages from cppreference.com
+//! - [`import`] - Import C++ reference pages from the offline archive
+//! - [`lint`] - Check the Markdown files for common mistakes
+//! - [`list`] - List the extracted references with their sources
 //! - [`mod@print`] - Concatenate HTML files for printing
+//! - [`prune`] - Delete or archive cached pages that are no longer referenced
+//! - [`reprocess`] - Regenerate cached pages from the pages as served
+//! - [`status`] - Compare the page caches with the Markdown files
 
 pub mod download;
+pub mod import;
+pub mod lint;
+pub mod list;
 pub mod print;
+pub mod prune;
+pub mod reprocess;
+pub mod status;
+
+use std::str::FromStr;
+
+/// Output format of commands producing a report
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON for other tooling
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("invalid format '{}', expected 'text' or 'json'", s)),
+        }
+    }
+}
@@ -3,153 +3,1115 @@
 //! This module provides functionality to download C++ reference pages from
 //! cppreference.com. It extracts URLs from Markdown files, downloads the
 //! corresponding HTML pages, and processes them by removing navigation elements.
+//! Pages are downloaded concurrently, within the per-host budget of the
+//! [`rate_limit`] module. Requests are sent by the [`fetch`] module, which
+//! checks response statuses and retries transient failures; a page that still
+//! fails is reported in the [`DownloadSummary`] without stopping the others.
+//! Pages are downloaded from the [`crate::sources`] edition of the language,
+//! and headers and cookies needed by some editions come from the [`profile`]
+//! of the language.
+//!
+//! With `assets`, the stylesheets, images and fonts used by the pages are
+//! downloaded by the [`assets`] module and linked locally.
+//!
+//! With `refresh`, cached pages are requested again with the validators
+//! recorded in the manifest, and only pages that changed upstream are
+//! rewritten.
+
+pub mod assets;
+pub mod cassette;
+pub mod fetch;
+pub mod profile;
+pub mod rate_limit;
+#[cfg(test)]
+pub(crate) mod test_server;
+pub mod validate;
 
-use log::{debug, info};
-use std::{fs, path::Path};
+use futures::{StreamExt, stream};
+use log::{debug, error, info, warn};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
 use tokio::time::Duration;
 
 use crate::{
-    errors::AppError, html::remove_navigation_elements, references::get_required_references,
+    cache::{PageCache, PageMetadata, QUARANTINE_DIR_NAME, file_name_for, sha256_hex},
+    config::Config,
+    errors::AppError,
+    html::{PROCESSING_VERSION, remove_navigation_elements},
+    references::{
+        CppReference, compare_cpp_names, get_required_references, normalize_url,
+        restrict_to_standard,
+        standard::{CppStandard, UnavailablePolicy},
+    },
+    sources::{Mirror, ReferenceSource, source_for},
+    utils::find_markdown_files,
 };
 
+use assets::{AssetDownloader, link_cached_assets};
+use cassette::CassetteMode;
+use fetch::{FetchedPage, HttpClient, RedirectPolicy, RetryPolicy, Validators, fetch_page};
+use profile::{CookieJar, RequestProfile};
+use rate_limit::RateLimiter;
+use validate::validate_page;
+
+/// Options for [`download_references`]
+#[derive(Debug, Clone)]
+pub struct DownloadOptions {
+    /// Directory containing the Markdown files with C++ reference links
+    pub contents_dir: PathBuf,
+    /// Directory the downloaded HTML pages are written to
+    pub output_dir: PathBuf,
+    /// Language version, selecting the source (see [`crate::sources`])
+    pub lang: String,
+    /// Whether to overwrite existing files
+    pub overwrite: bool,
+    /// Whether to request cached pages again and rewrite those that changed
+    pub refresh: bool,
+    /// The C++ standard supported by the judge, if entries should be checked against it
+    pub standard: Option<CppStandard>,
+    /// What to do with entries unavailable under `standard`
+    pub unavailable: UnavailablePolicy,
+    /// Maximum number of pages downloaded at once
+    pub concurrency: usize,
+    /// Maximum number of requests per second to each host (zero for no limit)
+    pub requests_per_second: f64,
+    /// Upper bound of the random delay added to every request
+    pub jitter: Duration,
+    /// Number of retries of a request failing with a transient error
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub retry_backoff: Duration,
+    /// Time after which a request is abandoned, and retried like other
+    /// transient failures
+    pub timeout: Duration,
+    /// Which redirects are accepted
+    pub redirects: RedirectPolicy,
+    /// Whether to rewrite the URLs of redirected pages in the Markdown files
+    pub fix_urls: bool,
+    /// Headers, user agent and cookies sent with every request
+    pub profile: RequestProfile,
+    /// Whether to download stylesheets, images and fonts and link them locally
+    pub assets: bool,
+    /// Whether to reject pages that are not complete reference pages in the
+    /// language of the source (see [`validate::validate_page`])
+    pub validate: bool,
+    /// URL the pages are downloaded from instead of the origin of the source
+    /// (see [`Mirror`])
+    pub base_url: Option<String>,
+    /// Whether to record the HTTP exchanges in a cassette or replay them from one
+    pub cassette: Option<CassetteMode>,
+}
+
+impl DownloadOptions {
+    /// Create options using the default directory layout
+    ///
+    /// Markdown files are read from `./contents` and pages are written to
+    /// `./cppreference_{lang}`. Existing files are not overwritten. Up to 4
+    /// pages are downloaded at once, with 2 requests per second to each host
+    /// and up to 250 ms of jitter. Requests time out after 30 s, and transient
+    /// failures are retried 3 times, starting after 500 ms. Redirects to the
+    /// same host are accepted. Assets are downloaded along with the pages, and
+    /// pages are validated.
+    pub fn new(lang: &str) -> Self {
+        Config::default().download_options(lang)
+    }
+}
+
+/// What happened to a reference during a download
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DownloadStatus {
+    /// The page was already cached
+    Skipped,
+    /// The page was requested again but has not changed
+    Unchanged,
+    /// The page was downloaded and stored
+    Succeeded,
+    /// The page could not be downloaded
+    Failed,
+}
+
+impl DownloadStatus {
+    /// The status as shown in the summary table
+    pub fn name(self) -> &'static str {
+        match self {
+            DownloadStatus::Skipped => "skipped",
+            DownloadStatus::Unchanged => "unchanged",
+            DownloadStatus::Succeeded => "succeeded",
+            DownloadStatus::Failed => "failed",
+        }
+    }
+}
+
+/// The outcome of a single reference
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadEntry {
+    /// The name the page is stored under
+    pub name: String,
+    /// What happened to the page
+    pub status: DownloadStatus,
+    /// Why the download failed
+    pub error: Option<String>,
+    /// The redirect the page was served through
+    pub redirect: Option<Redirect>,
+}
+
+/// An accepted redirect
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// The URL in the Markdown files
+    pub from: String,
+    /// The URL the page was served from
+    pub to: String,
+}
+
+/// The outcome of a download run
+///
+/// Entries are sorted by status (skipped, unchanged, succeeded, failed) and
+/// then by name, so failures end up next to the totals.
+#[derive(Debug, Clone, Default)]
+pub struct DownloadSummary {
+    /// The outcome of every reference
+    pub entries: Vec<DownloadEntry>,
+}
+
+impl DownloadSummary {
+    /// The number of references with a status
+    pub fn count(&self, status: DownloadStatus) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .count()
+    }
+
+    /// Format the summary as a table followed by the totals
+    pub fn render(&self) -> String {
+        let width = self
+            .entries
+            .iter()
+            .map(|entry| entry.name.chars().count())
+            .chain(std::iter::once("Reference".len()))
+            .max()
+            .unwrap_or_default();
+
+        let mut output = format!("{:<width$}  {:<9}  Details\n", "Reference", "Status");
+        for entry in &self.entries {
+            let details = match (&entry.error, &entry.redirect) {
+                (Some(error), _) => error.clone(),
+                (None, Some(redirect)) => format!("redirected to {}", redirect.to),
+                (None, None) => String::new(),
+            };
+            let line = format!(
+                "{:<width$}  {:<9}  {}",
+                entry.name,
+                entry.status.name(),
+                details
+            );
+            output.push_str(line.trim_end());
+            output.push('\n');
+        }
+        output.push_str(&format!("{}\n", self.totals()));
+        output
+    }
+
+    /// The number of references with each status
+    pub fn totals(&self) -> String {
+        format!(
+            "{} succeeded, {} unchanged, {} skipped, {} failed",
+            self.count(DownloadStatus::Succeeded),
+            self.count(DownloadStatus::Unchanged),
+            self.count(DownloadStatus::Skipped),
+            self.count(DownloadStatus::Failed)
+        )
+    }
+
+    /// Check whether every page is available
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::DownloadFailed`] if any page failed to download.
+    pub fn check(&self) -> Result<(), AppError> {
+        match self.count(DownloadStatus::Failed) {
+            0 => Ok(()),
+            failed => Err(AppError::DownloadFailed {
+                failed,
+                total: self.entries.len(),
+            }),
+        }
+    }
+
+    /// The redirects pages were served through
+    pub fn redirects(&self) -> impl Iterator<Item = &Redirect> {
+        self.entries
+            .iter()
+            .filter_map(|entry| entry.redirect.as_ref())
+    }
+
+    /// Record the outcome of a reference
+    pub(crate) fn push(&mut self, name: &str, status: DownloadStatus, error: Option<String>) {
+        self.entries.push(DownloadEntry {
+            name: name.to_string(),
+            status,
+            error,
+            redirect: None,
+        });
+    }
+
+    /// Sort the entries by status and then by name
+    pub(crate) fn sort(&mut self) {
+        self.entries.sort_by(|a, b| {
+            a.status
+                .cmp(&b.status)
+                .then_with(|| compare_cpp_names(&a.name, &b.name))
+        });
+    }
+}
+
 /// Download C++ reference pages from cppreference.com
 ///
 /// This function:
-/// 1. Creates the output directory (`./cppreference`) if it doesn't exist
-/// 2. Gets all required C++ references from Markdown files in `./contents`
-/// 3. Downloads the HTML pages (only missing ones unless `overwrite` is true)
-/// 4. Processes each HTML file to remove navigation elements
+/// 1. Creates the output directory if it doesn't exist
+/// 2. Gets all required C++ references from Markdown files in the contents directory
+/// 3. Downloads the HTML pages concurrently (only missing ones unless `overwrite` is set)
+/// 4. Processes each HTML file to remove navigation elements and, if `assets`
+///    is set, downloads its stylesheets and images and links them locally
+/// 5. Records the pages in the manifest of the output directory
+/// 6. Rewrites the URLs of redirected pages in the Markdown files if `fix_urls` is set
+///
+/// If a standard is selected, pages unavailable under it are either skipped
+/// or downloaded with a warning, depending on the policy.
 ///
 /// # Arguments
 ///
-/// * `overwrite` - Whether to overwrite existing files
+/// * `options` - Directories, language, overwrite behavior, standard and concurrency
 ///
 /// # Returns
 ///
-/// Returns `Ok(())` on success, or an error if something goes wrong.
+/// The outcome of every reference. Pages that fail to download do not stop
+/// the others; use [`DownloadSummary::check`] to turn failures into an error.
 ///
 /// # Errors
 ///
 /// Returns an error if:
+/// - There is no source for the language ([`AppError::UnknownLanguage`])
+/// - The base URL is invalid ([`AppError::InvalidBaseUrl`])
+/// - The request rate is too small ([`AppError::InvalidRequestRate`])
 /// - The output directory cannot be created
 /// - Reference extraction fails
-/// - Download fails
-/// - File writing fails
-pub async fn download_references(overwrite: bool, lang: &str) -> Result<(), AppError> {
-    info!("Starting C++ reference downloader (language: {})", lang);
+/// - The HTTP client cannot be created or the cookies file cannot be loaded
+/// - File writing fails, including the Markdown files with `fix_urls`
+pub async fn download_references(options: &DownloadOptions) -> Result<DownloadSummary, AppError> {
+    let site = source_for(&options.lang)?;
+    let mirror;
+    let source: &dyn ReferenceSource = match &options.base_url {
+        Some(base_url) => {
+            mirror = Mirror::new(site, base_url)?;
+            &mirror
+        }
+        None => site,
+    };
+    info!(
+        "Starting C++ reference downloader (source: {})",
+        source.description()
+    );
 
-    let output_dir_name = format!("./cppreference_{}", lang);
-    let output_dir = Path::new(&output_dir_name);
+    let output_dir = options.output_dir.as_path();
     if !output_dir.exists() {
         info!("Creating output directory: {:?}", output_dir);
         fs::create_dir_all(output_dir)?;
     }
 
-    let unique_references = get_required_references()?;
+    let mut unique_references = get_required_references(&options.contents_dir)?;
+    if let Some(standard) = options.standard {
+        match options.unavailable {
+            UnavailablePolicy::Exclude => restrict_to_standard(&mut unique_references, standard),
+            UnavailablePolicy::Flag => {
+                for reference in unique_references.values() {
+                    if !reference.is_available(standard) {
+                        warn!("{} is not available in {}", reference.name, standard);
+                    }
+                }
+            }
+        }
+    }
 
     info!(
         "Found {} unique references to download",
         unique_references.len()
     );
 
-    download_files(unique_references, overwrite, lang).await?;
+    let summary = download_files(unique_references, output_dir, source, options).await?;
 
-    info!("Download completed successfully");
-    Ok(())
+    info!("Download completed: {}", summary.totals());
+
+    if options.fix_urls {
+        // The Markdown files link to English pages, so redirects within another
+        // edition are mapped back to English URLs.
+        let mut redirects = Vec::new();
+        for redirect in summary.redirects() {
+            match source.canonical_url(&redirect.to) {
+                Some(to) if normalize_url(&to) != normalize_url(&redirect.from) => {
+                    redirects.push(Redirect {
+                        from: redirect.from.clone(),
+                        to,
+                    });
+                }
+                Some(_) => {}
+                None => warn!(
+                    "Not fixing {}: {} is not a page of {}",
+                    redirect.from,
+                    redirect.to,
+                    source.description()
+                ),
+            }
+        }
+        if !redirects.is_empty() {
+            let redirects: Vec<&Redirect> = redirects.iter().collect();
+            let fixed = fix_markdown_urls(&options.contents_dir, &redirects)?;
+            info!("Rewrote {} redirected link(s) in the Markdown files", fixed);
+        }
+    }
+    Ok(summary)
 }
 
 /// Download HTML files from cppreference.com
 ///
-/// This function downloads HTML files for each reference, skipping files that
-/// already exist unless `overwrite` is true. It also processes each HTML file
-/// to remove navigation elements.
+/// This function downloads HTML files for each reference, skipping pages that
+/// are already cached unless `overwrite` or `refresh` is true. When
+/// refreshing, the request is conditional on the recorded `ETag` and
+/// `Last-Modified` validators, and a page is only rewritten if its processed
+/// content differs from the cached file. Pages processed by an older version
+/// or with assets linked differently are requested unconditionally so that
+/// they are processed again.
+///
+/// Up to `concurrency` pages are downloaded at once, and requests to each host
+/// are spaced out by the rate limiter. Pages are stored under escaped file
+/// names (see [`crate::cache`]) and recorded in the manifest as they are
+/// written, so an interrupted download can be resumed.
 ///
 /// # Arguments
 ///
 /// * `references` - A HashMap of CppReference structs keyed by name
-/// * `overwrite` - Whether to overwrite existing files
+/// * `output_dir` - The directory to write the HTML files to
+/// * `source` - The edition of cppreference to download from
+/// * `options` - Overwrite behavior and concurrency settings
 ///
 /// # Returns
 ///
-/// Returns `Ok(())` on success, or an error if download or writing fails.
+/// The outcome of every reference, or an error if the cache cannot be written.
 async fn download_files(
-    references: std::collections::HashMap<String, crate::references::CppReference>,
-    overwrite: bool,
-    lang: &str,
-) -> Result<(), AppError> {
-    let client = reqwest::Client::builder()
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36")
-        .build()?;
-
-    let output_dir_name = format!("./cppreference_{}", lang);
-    let output_dir = Path::new(&output_dir_name);
-
-    for (name, ref_item) in references {
-        let filename = format!("{}.html", name);
-        let output_path = output_dir.join(&filename);
-
-        if output_path.exists() && !overwrite {
-            debug!("File already exists: {}.html, skipping download", name);
+    references: HashMap<String, CppReference>,
+    output_dir: &Path,
+    source: &dyn ReferenceSource,
+    options: &DownloadOptions,
+) -> Result<DownloadSummary, AppError> {
+    let client = HttpClient::new(
+        options.profile.client(options.timeout)?,
+        options.cassette.as_ref(),
+    );
+    let host = reqwest::Url::parse(&source.origin())
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_default();
+    let cookies = options.profile.cookie_jar(&options.lang, &host)?;
+    let limiter = match client {
+        // Replayed responses are not rate limited, since no server is involved
+        HttpClient::Replaying(_) => RateLimiter::new(0.0, Duration::ZERO)?,
+        _ => RateLimiter::new(options.requests_per_second, options.jitter)?,
+    };
+
+    let mut cache = PageCache::open(output_dir)?;
+    let mut summary = DownloadSummary::default();
+
+    let mut pending: Vec<(String, CppReference, Validators)> = Vec::new();
+    for (name, reference) in references {
+        let entry = cache.entry(&name);
+        if entry.is_some() && !options.overwrite && !options.refresh {
+            debug!(
+                "{} is already cached as {:?}, skipping download",
+                name,
+                cache.page_path(&name)
+            );
+            summary.push(&name, DownloadStatus::Skipped, None);
             continue;
         }
 
-        // URL 转换
-        //
-        // 中文版：
-        // 1. 域名：en.cppreference.com → cppreference.cn
-        // 2. 后缀：移除 .html（中文版 URL 没有 .html 后缀）
-        //
-        // 示例：
-        //   英文: https://en.cppreference.com/w/cpp/numeric/bit_floor.html
-        //   中文: https://cppreference.cn/w/cpp/numeric/bit_floor
-        let url = if lang == "zh" {
-            ref_item.url.replace("en.cppreference.com", "cppreference.cn").trim_end_matches(".html").to_string()
-        } else {
-            ref_item.url.clone()
+        let validators = match entry {
+            Some(entry) if !options.overwrite && entry.metadata.is_current(options.assets) => {
+                Validators {
+                    etag: entry.metadata.etag.clone(),
+                    last_modified: entry.metadata.last_modified.clone(),
+                }
+            }
+            _ => Validators::default(),
         };
+        pending.push((name, reference, validators));
+    }
+    pending.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let concurrency = options.concurrency.max(1);
+    info!(
+        "Downloading {} page(s) with up to {} concurrent request(s)",
+        pending.len(),
+        concurrency
+    );
+
+    let retry = RetryPolicy {
+        retries: options.retries,
+        backoff: options.retry_backoff,
+    };
+    let assets = AssetDownloader::new(&cache, &client, &limiter, &cookies, retry);
+    let downloader = Downloader {
+        client: &client,
+        limiter: &limiter,
+        cookies: &cookies,
+        retry,
+        assets: options.assets.then_some(&assets),
+        redirects: options.redirects,
+        source,
+        quarantine_dir: options
+            .validate
+            .then(|| output_dir.join(QUARANTINE_DIR_NAME)),
+    };
+    let downloader = &downloader;
+    let mut downloads = stream::iter(pending)
+        .map(|(name, ref_item, validators)| async move {
+            let result = downloader
+                .download_page(&name, &ref_item.url, &validators)
+                .await;
+            (name, ref_item.url, result)
+        })
+        .buffer_unordered(concurrency);
+
+    while let Some((name, url, result)) = downloads.next().await {
+        let fetched_at = Some(unix_time());
+        let page = match result {
+            Ok(Some(page)) => page,
+            Ok(None) => {
+                let Some(entry) = cache.entry(&name) else {
+                    error!("{} has not been modified, but it is not cached", name);
+                    summary.push(
+                        &name,
+                        DownloadStatus::Failed,
+                        Some("not modified, but not cached".to_string()),
+                    );
+                    continue;
+                };
+                debug!("{} has not been modified", name);
+                let mut metadata = entry.metadata.clone();
+                metadata.fetched_at = fetched_at;
+                cache.insert(&name, metadata);
+                cache.save()?;
+                summary.push(&name, DownloadStatus::Unchanged, None);
+                continue;
+            }
+            Err(e) => {
+                error!("Failed to download {}: {}", name, e);
+                summary.push(&name, DownloadStatus::Failed, Some(e.to_string()));
+                continue;
+            }
+        };
+
+        if options.assets {
+            assets.record(&mut cache);
+        }
+        let content = match process_page(
+            &cache,
+            &page.body,
+            &name,
+            &page.final_url,
+            source.navigation_selectors(),
+            options.assets,
+        ) {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to process {}: {}", name, e);
+                summary.push(&name, DownloadStatus::Failed, Some(e.to_string()));
+                continue;
+            }
+        };
+
+        let raw_sha256 = cache.write_raw(&name, &page.body)?;
+        let output_path = cache.page_path(&name);
+        let sha256 = sha256_hex(content.as_bytes());
+        let unchanged = output_path.is_file()
+            && cache.entry(&name).is_some_and(|entry| {
+                entry.metadata.sha256.as_ref() == Some(&sha256)
+                    && entry.metadata.is_current(options.assets)
+            });
+        if !unchanged {
+            fs::write(&output_path, &content)?;
+            debug!("Saved {} to {:?}", name, output_path);
+        }
+        let redirect =
+            (normalize_url(&page.url) != normalize_url(&page.final_url)).then(|| Redirect {
+                from: url,
+                to: page.final_url.clone(),
+            });
+        cache.insert(
+            &name,
+            PageMetadata {
+                url: Some(page.url),
+                final_url: Some(page.final_url),
+                fetched_at,
+                etag: page.etag,
+                last_modified: page.last_modified,
+                sha256: Some(sha256),
+                processing_version: Some(PROCESSING_VERSION),
+                assets_linked: options.assets,
+                raw_sha256: Some(raw_sha256),
+                archive: None,
+            },
+        );
+        cache.save()?;
+        summary.entries.push(DownloadEntry {
+            name,
+            status: if unchanged {
+                DownloadStatus::Unchanged
+            } else {
+                DownloadStatus::Succeeded
+            },
+            error: None,
+            redirect,
+        });
+    }
+
+    summary.sort();
+    Ok(summary)
+}
+
+/// Everything needed to download a page, shared by concurrent downloads
+struct Downloader<'a> {
+    /// The HTTP client
+    client: &'a HttpClient,
+    /// The rate limiter shared by all downloads
+    limiter: &'a RateLimiter,
+    /// The cookies sent with every request
+    cookies: &'a CookieJar,
+    /// How failed requests are retried
+    retry: RetryPolicy,
+    /// Downloads the assets of every page, if assets are downloaded
+    assets: Option<&'a AssetDownloader<'a>>,
+    /// Which redirects are accepted
+    redirects: RedirectPolicy,
+    /// The edition of cppreference the pages are downloaded from
+    source: &'a dyn ReferenceSource,
+    /// Where rejected pages are kept, if pages are validated
+    quarantine_dir: Option<PathBuf>,
+}
+
+impl Downloader<'_> {
+    /// Download and validate a single page, and then its assets
+    ///
+    /// A page failing validation is written to the quarantine directory as
+    /// served, instead of replacing the cached copy.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the reference, for logging
+    /// * `url` - The English URL of the page
+    /// * `validators` - Validators of the cached copy, if the request should be conditional
+    ///
+    /// # Returns
+    ///
+    /// The page as served, or `None` if it has not been modified.
+    async fn download_page(
+        &self,
+        name: &str,
+        url: &str,
+        validators: &Validators,
+    ) -> Result<Option<FetchedPage>, AppError> {
+        let url = self.source.localize_url(url);
 
         info!("Downloading {} from {}", name, url);
 
-        let request_builder = client.get(&url);
-        if lang == "zh" {
-            // 可选：添加浏览器请求头以避免被重定向到英文版
-            // 如需使用，请取消注释并根据需要填写
-            // request_builder = request_builder
-            //     .header("Accept-Language", "zh-CN,zh-Hans;q=0.9")
-            //     .header("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
-            //     .header("Sec-Fetch-Dest", "document")
-            //     .header("Sec-Fetch-Mode", "navigate")
-            //     .header("Sec-Fetch-Site", "none")
-            //     .header("Cookie", "在此处填入你的浏览器 Cookie");
-        }
-        let response = request_builder.send().await?;
-        let final_url = response.url().to_string();
+        let Some(page) = fetch_page(
+            self.client,
+            self.limiter,
+            self.cookies,
+            &url,
+            validators,
+            self.retry,
+        )
+        .await?
+        else {
+            return Ok(None);
+        };
 
         // 重定向检查
         //
-        // 此检查确保我们下载的 URL 与请求的 URL 完全一致
-        // 主要用途：
-        // 1. 中文版下载时：防止被重定向到 en.cppreference.com 英文版
-        // 2. 确保下载到的是我们真正期望的页面，而不是其他语言或错误页面
-        //
-        // 注意：如果正常的重定向（如旧 URL 重定向到新 URL）也会被阻止
-        // 如需允许重定向，请注释掉此检查
-        if final_url != url {
-            return Err(AppError::IoError(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("URL {} redirected to {}", url, final_url),
-            )));
+        // 根据重定向策略检查最终 URL：
+        // - forbid：任何重定向都会失败
+        // - same-host（默认）：允许同一站点内的重定向（如旧 URL 重定向到新 URL），
+        //   但中文版被重定向到 en.cppreference.com 英文版时会失败
+        // - follow：接受所有重定向
+        if self.redirects.check(&url, &page.final_url)? {
+            warn!("{} redirected to {}", url, page.final_url);
+        }
+
+        if let Some(dir) = &self.quarantine_dir {
+            let path = dir.join(file_name_for(name));
+            if let Err(e) = validate_page(&page.body, &page.final_url, self.source) {
+                fs::create_dir_all(dir)?;
+                fs::write(&path, &page.body)?;
+                warn!("Quarantined the page of {} as {:?}", name, path);
+                return Err(e);
+            }
+            if path.is_file() {
+                fs::remove_file(&path)?;
+            }
+        }
+
+        if let Some(assets) = self.assets {
+            assets
+                .download_page_assets(&page.body, &page.final_url)
+                .await?;
+        }
+        Ok(Some(page))
+    }
+}
+
+/// Process a page as served with the current pipeline ([`PROCESSING_VERSION`])
+///
+/// Navigation elements are removed and, if `assets` is set, links to
+/// stylesheets and images are pointed to the copies in the cache (see
+/// [`assets::link_cached_assets`]). The same pipeline is used for downloaded
+/// pages and by `ref reprocess`, so both produce the same files.
+///
+/// # Arguments
+///
+/// * `cache` - The cache the page is stored in
+/// * `raw` - The HTML content as served
+/// * `name` - The name of the reference, for logging
+/// * `page_url` - The URL the page was served from, to resolve asset links against
+/// * `selectors` - Selectors of the navigation elements to remove
+/// * `assets` - Whether to link the cached assets
+///
+/// # Errors
+///
+/// Returns [`AppError::HtmlParsingError`] if a selector is invalid.
+pub(crate) fn process_page(
+    cache: &PageCache,
+    raw: &str,
+    name: &str,
+    page_url: &str,
+    selectors: &[&str],
+    assets: bool,
+) -> Result<String, AppError> {
+    let content = remove_navigation_elements(raw, name, selectors)?;
+    Ok(if assets {
+        link_cached_assets(cache, &content, page_url)
+    } else {
+        content
+    })
+}
+
+/// Replace the URLs of redirected pages in the Markdown files
+///
+/// Links are rewritten to the URL the page was served from, keeping any
+/// `#fragment`.
+///
+/// # Returns
+///
+/// The number of links rewritten.
+///
+/// # Errors
+///
+/// Returns an error if a Markdown file cannot be read or written.
+fn fix_markdown_urls(contents_dir: &Path, redirects: &[&Redirect]) -> Result<usize, AppError> {
+    let mut fixed = 0;
+    for file in find_markdown_files(contents_dir)? {
+        let content = fs::read_to_string(&file)?;
+        let mut updated = content.clone();
+        for redirect in redirects {
+            let from = redirect
+                .from
+                .split_once('#')
+                .map_or(redirect.from.as_str(), |(url, _)| url);
+            for end in [")", "#"] {
+                let old = format!("]({}{}", from, end);
+                let count = updated.matches(&old).count();
+                if count > 0 {
+                    updated = updated.replace(&old, &format!("]({}{}", redirect.to, end));
+                    fixed += count;
+                }
+            }
+        }
+        if updated != content {
+            info!("Fixing redirected URLs in {:?}", file);
+            fs::write(&file, updated)?;
         }
+    }
+    Ok(fixed)
+}
+
+/// The current time in seconds since the Unix epoch
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::ASSETS_DIR_NAME;
+    use test_server::{Route, TestServer, download, fixture_page, local_options, write_contents};
+
+    #[test]
+    fn test_summary_render_and_check() {
+        let mut summary = DownloadSummary::default();
+        summary.push("std::vector", DownloadStatus::Skipped, None);
+        summary.push("std::sort", DownloadStatus::Succeeded, None);
+        summary.push(
+            "std::foo",
+            DownloadStatus::Failed,
+            Some(
+                "HTTP status 404 Not Found for https://en.cppreference.com/w/cpp/foo.html"
+                    .to_string(),
+            ),
+        );
+        summary.entries.push(DownloadEntry {
+            name: "std::bar".to_string(),
+            status: DownloadStatus::Succeeded,
+            error: None,
+            redirect: Some(Redirect {
+                from: "https://en.cppreference.com/w/cpp/bar.html".to_string(),
+                to: "https://en.cppreference.com/w/cpp/baz.html".to_string(),
+            }),
+        });
+
+        assert_eq!(
+            summary.render(),
+            "Reference    Status     Details\n\
+             std::vector  skipped\n\
+             std::sort    succeeded\n\
+             std::foo     failed     HTTP status 404 Not Found for https://en.cppreference.com/w/cpp/foo.html\n\
+             std::bar     succeeded  redirected to https://en.cppreference.com/w/cpp/baz.html\n\
+             2 succeeded, 0 unchanged, 1 skipped, 1 failed\n"
+        );
+        assert!(matches!(
+            summary.check(),
+            Err(AppError::DownloadFailed {
+                failed: 1,
+                total: 4
+            })
+        ));
+
+        summary.entries.remove(2);
+        assert!(summary.check().is_ok());
+    }
+
+    #[test]
+    fn test_fix_markdown_urls() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file = temp_dir.path().join("Containers.md");
+        fs::write(
+            &file,
+            "| F | C++ |\n| - | - |\n\
+             | 构造 | [`std::vector`](https://en.cppreference.com/w/cpp/container/vector_old.html) |\n\
+             | 迭代 | [`std::vector::begin`](https://en.cppreference.com/w/cpp/container/vector_old.html#begin) |\n\
+             | 其他 | [`std::vector_other`](https://en.cppreference.com/w/cpp/container/vector_old.html_x) |\n",
+        )
+        .unwrap();
+
+        let redirect = Redirect {
+            from: "https://en.cppreference.com/w/cpp/container/vector_old.html".to_string(),
+            to: "https://en.cppreference.com/w/cpp/container/vector.html".to_string(),
+        };
+        assert_eq!(fix_markdown_urls(temp_dir.path(), &[&redirect]).unwrap(), 2);
+
+        let content = fs::read_to_string(&file).unwrap();
+        assert!(
+            content.contains(
+                "[`std::vector`](https://en.cppreference.com/w/cpp/container/vector.html)"
+            )
+        );
+        assert!(
+            content.contains("(https://en.cppreference.com/w/cpp/container/vector.html#begin)")
+        );
+        assert!(
+            content.contains("(https://en.cppreference.com/w/cpp/container/vector_old.html_x)")
+        );
+    }
+
+    #[test]
+    fn test_download_from_test_server() {
+        let other_site = TestServer::start([(
+            "/w/cpp/algorithm/moved".to_string(),
+            Route::Page(fixture_page("en", "std::moved")),
+        )]);
+        let page = |title: &str| Route::Page(fixture_page("zh-Hans", title));
+        let server = TestServer::start([
+            ("/w/cpp/algorithm/sort".to_string(), page("std::sort")),
+            (
+                "/w/cpp/algorithm/find_old".to_string(),
+                Route::Redirect("/w/cpp/algorithm/find".to_string()),
+            ),
+            ("/w/cpp/algorithm/find".to_string(), page("std::find")),
+            (
+                "/w/cpp/algorithm/count".to_string(),
+                Route::Slow(Duration::from_millis(300), Box::new(page("std::count"))),
+            ),
+            (
+                "/w/cpp/algorithm/fill".to_string(),
+                Route::Page(fixture_page("en", "std::fill")),
+            ),
+            (
+                "/w/cpp/algorithm/moved".to_string(),
+                Route::Redirect(format!(
+                    "{}/w/cpp/algorithm/moved",
+                    other_site.url().replace("127.0.0.1", "localhost")
+                )),
+            ),
+        ]);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let contents_dir = write_contents(
+            temp_dir.path(),
+            &[
+                ("std::sort", "cpp/algorithm/sort"),
+                ("std::find", "cpp/algorithm/find_old"),
+                ("std::count", "cpp/algorithm/count"),
+                ("std::fill", "cpp/algorithm/fill"),
+                ("std::moved", "cpp/algorithm/moved"),
+                ("std::missing", "cpp/algorithm/missing"),
+            ],
+        );
+        let markdown = contents_dir.join("Algorithms.md");
+        let mut options = local_options(
+            &contents_dir,
+            &temp_dir.path().join("cppreference_zh"),
+            &format!("{}/", server.url()),
+        );
+        options.fix_urls = true;
+
+        let summary = download(&options);
 
-        let content = response.text().await?;
+        let outcome: Vec<(&str, DownloadStatus)> = summary
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            outcome,
+            [
+                ("std::count", DownloadStatus::Succeeded),
+                ("std::find", DownloadStatus::Succeeded),
+                ("std::sort", DownloadStatus::Succeeded),
+                ("std::fill", DownloadStatus::Failed),
+                ("std::missing", DownloadStatus::Failed),
+                ("std::moved", DownloadStatus::Failed),
+            ]
+        );
+        let error = |name: &str| {
+            let entry = summary.entries.iter().find(|entry| entry.name == name);
+            entry.unwrap().error.clone().unwrap()
+        };
+        assert!(error("std::fill").contains("language en instead of zh"));
+        assert!(error("std::missing").starts_with("HTTP status 404"));
+        assert!(error("std::moved").contains("redirected to http://localhost:"));
+
+        // The Chinese URLs have no .html suffix and are served by the test server
+        let requests = server.requests();
+        assert!(requests.contains(&"/w/cpp/algorithm/sort".to_string()));
+        assert!(!requests.iter().any(|path| path.ends_with(".html")));
+
+        // Navigation is stripped and rejected pages are quarantined
+        let cache = PageCache::open(&options.output_dir).unwrap();
+        let sort = fs::read_to_string(cache.page_path("std::sort")).unwrap();
+        assert!(sort.contains(r#"id="firstHeading""#));
+        assert!(!sort.contains("t-navbar") && !sort.contains("mw-head"));
+        let raw = fs::read_to_string(cache.raw_path("std::sort")).unwrap();
+        assert!(raw.contains("t-navbar") && raw.contains("mw-head"));
+        assert!(cache.entry("std::fill").is_none());
+        assert!(
+            options
+                .output_dir
+                .join(QUARANTINE_DIR_NAME)
+                .join(file_name_for("std::fill"))
+                .is_file()
+        );
+
+        // The redirect within the site is recorded and fixed in the Markdown files
+        let find = cache.entry("std::find").unwrap();
+        assert_eq!(
+            find.metadata.final_url,
+            Some(format!("{}/w/cpp/algorithm/find", server.url()))
+        );
+        let content = fs::read_to_string(&markdown).unwrap();
+        assert!(content.contains("(https://en.cppreference.com/w/cpp/algorithm/find.html)"));
+    }
+
+    #[test]
+    fn test_stalled_requests_time_out() {
+        let server = TestServer::start([(
+            "/w/cpp/algorithm/sort".to_string(),
+            Route::Slow(
+                Duration::from_secs(5),
+                Box::new(Route::Page(fixture_page("zh-Hans", "std::sort"))),
+            ),
+        )]);
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let contents_dir = write_contents(temp_dir.path(), &[("std::sort", "cpp/algorithm/sort")]);
+        let mut options = local_options(
+            &contents_dir,
+            &temp_dir.path().join("cppreference_zh"),
+            &server.url(),
+        );
+        options.retries = 1;
+        options.retry_backoff = Duration::ZERO;
+        options.timeout = Duration::from_millis(200);
+
+        let started = std::time::Instant::now();
+        let summary = download(&options);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(summary.count(DownloadStatus::Failed), 1);
+        // The timeout is transient, so the request was retried
+        assert_eq!(server.requests().len(), 2);
+    }
 
-        let processed_content = remove_navigation_elements(&content, &name)?;
+    #[test]
+    fn test_asset_errors_fail_their_page() {
+        let page = |title: &str| Route::Page(fixture_page("zh-Hans", title));
+        let with_image =
+            fixture_page("zh-Hans", "std::sort").replace("<p>", r#"<p><img src="/logo.png">"#);
+        let server = TestServer::start([
+            ("/w/cpp/algorithm/sort".to_string(), Route::Page(with_image)),
+            ("/w/cpp/algorithm/find".to_string(), page("std::find")),
+            ("/logo.png".to_string(), Route::Page("png".to_string())),
+        ]);
 
-        fs::write(output_path, processed_content)?;
-        debug!("Saved {} to {}/{}.html", name, output_dir_name, name);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let contents_dir = write_contents(
+            temp_dir.path(),
+            &[
+                ("std::sort", "cpp/algorithm/sort"),
+                ("std::find", "cpp/algorithm/find"),
+            ],
+        );
+        let options = local_options(
+            &contents_dir,
+            &temp_dir.path().join("cppreference_zh"),
+            &server.url(),
+        );
+        // A file in place of the assets directory makes storing assets fail
+        fs::create_dir(&options.output_dir).unwrap();
+        fs::write(options.output_dir.join(ASSETS_DIR_NAME), "").unwrap();
 
-        tokio::time::sleep(Duration::from_millis(500)).await;
+        let summary = download(&options);
+        let outcome: Vec<(&str, DownloadStatus)> = summary
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            outcome,
+            [
+                ("std::find", DownloadStatus::Succeeded),
+                ("std::sort", DownloadStatus::Failed),
+            ]
+        );
     }
 
-    Ok(())
+    #[test]
+    fn test_record_and_replay() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let contents_dir = write_contents(
+            temp_dir.path(),
+            &[
+                ("std::sort", "cpp/algorithm/sort"),
+                ("std::missing", "cpp/algorithm/missing"),
+            ],
+        );
+        let cassette_dir = temp_dir.path().join("cassette");
+
+        let run = |server_url: &str, output: &str, cassette: CassetteMode, refresh: bool| {
+            let mut options =
+                local_options(&contents_dir, &temp_dir.path().join(output), server_url);
+            options.refresh = refresh;
+            options.cassette = Some(cassette);
+            let summary = download(&options);
+            let outcome: Vec<(String, DownloadStatus, Option<String>)> = summary
+                .entries
+                .into_iter()
+                .map(|entry| (entry.name, entry.status, entry.error))
+                .collect();
+            let page = PageCache::open(&options.output_dir)
+                .ok()
+                .and_then(|cache| fs::read_to_string(cache.page_path("std::sort")).ok());
+            (outcome, page)
+        };
+
+        let server = TestServer::start([(
+            "/w/cpp/algorithm/sort".to_string(),
+            Route::Page(fixture_page("zh-Hans", "std::sort")),
+        )]);
+        let server_url = server.url();
+        let recorded = run(
+            &server_url,
+            "recorded",
+            CassetteMode::Record(cassette_dir.clone()),
+            false,
+        );
+        assert_eq!(recorded.0[0].1, DownloadStatus::Succeeded);
+        assert_eq!(recorded.0[1].1, DownloadStatus::Failed);
+        assert!(recorded.1.is_some());
+
+        // Refreshing answers `304 Not Modified`, which keeps the recorded page
+        let refreshed = run(
+            &server_url,
+            "recorded",
+            CassetteMode::Record(cassette_dir.clone()),
+            true,
+        );
+        assert_eq!(refreshed.0[0].1, DownloadStatus::Unchanged);
+        // A cassette holding only the `304` cannot fill another directory
+        let refresh_dir = temp_dir.path().join("refresh_cassette");
+        run(
+            &server_url,
+            "recorded",
+            CassetteMode::Record(refresh_dir.clone()),
+            true,
+        );
+        drop(server);
+
+        // The server is gone, so every response comes from the cassette
+        let replayed = run(
+            &server_url,
+            "replayed",
+            CassetteMode::Replay(cassette_dir.clone()),
+            false,
+        );
+        assert_eq!(replayed, recorded);
+        let (outcome, page) = run(
+            &server_url,
+            "replayed_refresh",
+            CassetteMode::Replay(refresh_dir),
+            false,
+        );
+        assert!(page.is_none());
+        assert_eq!(outcome[1].1, DownloadStatus::Failed);
+        assert_eq!(
+            outcome[1].2.as_deref(),
+            Some("not modified, but not cached")
+        );
+
+        // Requests that were not recorded fail
+        let (outcome, page) = run(
+            "http://127.0.0.1:9",
+            "unrecorded",
+            CassetteMode::Replay(cassette_dir),
+            false,
+        );
+        assert!(page.is_none());
+        for (_, status, error) in outcome {
+            assert_eq!(status, DownloadStatus::Failed);
+            assert!(error.unwrap().starts_with("No recorded response for"));
+        }
+    }
 }
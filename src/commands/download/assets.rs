@@ -0,0 +1,253 @@
+//! Downloading page assets
+//!
+//! Stylesheets and images referenced by downloaded pages are stored in the
+//! assets directory of the cache (see [`crate::cache`]), named after the
+//! SHA-256 hash of their content, so that an asset shared by many pages or
+//! served under several URLs is stored once. Fonts, images and stylesheets
+//! referenced by stylesheets are downloaded as well. The links in stylesheets
+//! are rewritten to the local files when they are stored, and the links in
+//! pages by [`link_cached_assets`], so that the pages render without network
+//! access.
+//!
+//! An asset that cannot be downloaded is reported with a warning and its link
+//! is made absolute, so it keeps pointing to the remote copy; it does not fail
+//! the page. An asset that cannot be stored fails the page it belongs to.
+
+use log::{debug, warn};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use super::{
+    fetch::{HttpClient, RetryPolicy, fetch_asset},
+    profile::CookieJar,
+    rate_limit::RateLimiter,
+};
+use crate::{
+    cache::{ASSETS_DIR_NAME, PageCache, sha256_hex},
+    errors::AppError,
+    html::{asset_extension, asset_urls, css_urls, rewrite_asset_urls, rewrite_css_urls},
+};
+
+/// How deeply stylesheets importing other stylesheets are followed
+const MAX_IMPORT_DEPTH: u32 = 4;
+
+/// Downloads the assets of pages into a cache directory
+///
+/// The downloader is shared by concurrent page downloads. The assets it knows
+/// about are recorded in the manifest with [`AssetDownloader::record`].
+pub struct AssetDownloader<'a> {
+    /// The HTTP client
+    client: &'a HttpClient,
+    /// The rate limiter shared by all downloads
+    limiter: &'a RateLimiter,
+    /// The cookies sent with every request
+    cookies: &'a CookieJar,
+    /// How failed requests are retried
+    retry: RetryPolicy,
+    /// The directory assets are stored in
+    dir: PathBuf,
+    /// File names of the stored assets, relative to `dir`, keyed by URL
+    files: Mutex<BTreeMap<String, String>>,
+    /// URLs that failed during this run, so they are not requested again
+    failed: Mutex<HashSet<String>>,
+}
+
+impl<'a> AssetDownloader<'a> {
+    /// Create an asset downloader for the assets directory of a cache
+    pub fn new(
+        cache: &PageCache,
+        client: &'a HttpClient,
+        limiter: &'a RateLimiter,
+        cookies: &'a CookieJar,
+        retry: RetryPolicy,
+    ) -> Self {
+        AssetDownloader {
+            client,
+            limiter,
+            cookies,
+            retry,
+            dir: cache.assets_dir(),
+            files: Mutex::new(cache.manifest().assets.clone()),
+            failed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Download the assets of a page into the assets directory
+    ///
+    /// Assets already stored are not requested again. Use
+    /// [`AssetDownloader::record`] and then [`link_cached_assets`] to point the
+    /// links of the page to them.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The HTML content of the page
+    /// * `page_url` - The URL the page was served from, to resolve relative links against
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an asset cannot be written to the assets directory.
+    pub async fn download_page_assets(
+        &self,
+        content: &str,
+        page_url: &str,
+    ) -> Result<(), AppError> {
+        for url in asset_urls(content) {
+            if let Some(absolute) = resolve(page_url, &url) {
+                self.localize(&absolute, 0).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Record the stored assets in the manifest of the cache
+    pub fn record(&self, cache: &mut PageCache) {
+        for (url, file) in self.files.lock().unwrap().iter() {
+            if cache.asset(url) != Some(file.as_str()) {
+                cache.insert_asset(url, file);
+            }
+        }
+    }
+
+    /// Download an asset unless it is stored
+    ///
+    /// # Returns
+    ///
+    /// The file name of the asset in the assets directory, or `None` if it
+    /// could not be downloaded.
+    async fn localize(&self, url: &str, depth: u32) -> Result<Option<String>, AppError> {
+        if let Some(file) = self.files.lock().unwrap().get(url) {
+            return Ok(Some(file.clone()));
+        }
+        if self.failed.lock().unwrap().contains(url) {
+            return Ok(None);
+        }
+
+        debug!("Downloading asset {}", url);
+        let asset =
+            match fetch_asset(self.client, self.limiter, self.cookies, url, self.retry).await {
+                Ok(asset) => asset,
+                Err(e) => {
+                    warn!("Failed to download asset {}: {}", url, e);
+                    self.failed.lock().unwrap().insert(url.to_string());
+                    return Ok(None);
+                }
+            };
+
+        let extension = asset_extension(asset.content_type.as_deref(), url);
+        let mut body = asset.body;
+        if extension == "css" {
+            body = self
+                .localize_stylesheet(&String::from_utf8_lossy(&body), url, depth)
+                .await?
+                .into_bytes();
+        }
+
+        let file = format!("{}.{}", sha256_hex(&body), extension);
+        let path = self.dir.join(&file);
+        if !path.is_file() {
+            fs::create_dir_all(&self.dir)?;
+            fs::write(&path, &body)?;
+        }
+        self.files
+            .lock()
+            .unwrap()
+            .insert(url.to_string(), file.clone());
+        Ok(Some(file))
+    }
+
+    /// Download the assets of a stylesheet and point its links to the local copies
+    ///
+    /// Assets are stored next to the stylesheet, so the links are plain file names.
+    async fn localize_stylesheet(
+        &self,
+        css: &str,
+        url: &str,
+        depth: u32,
+    ) -> Result<String, AppError> {
+        if depth >= MAX_IMPORT_DEPTH {
+            warn!("Not following the assets of {}: imported too deeply", url);
+            return Ok(css.to_string());
+        }
+
+        let mut local = HashMap::new();
+        for reference in css_urls(css) {
+            let Some(absolute) = resolve(url, &reference) else {
+                continue;
+            };
+            let replacement = Box::pin(self.localize(&absolute, depth + 1))
+                .await?
+                .unwrap_or(absolute);
+            local.insert(reference, replacement);
+        }
+        Ok(rewrite_css_urls(css, |reference| {
+            local.get(reference).cloned()
+        }))
+    }
+}
+
+/// Point the asset links of a page to the copies in the cache
+///
+/// No request is made, so this also works offline.
+///
+/// # Arguments
+///
+/// * `cache` - The cache the page is stored in
+/// * `content` - The HTML content of the page
+/// * `page_url` - The URL the page was served from, to resolve relative links against
+///
+/// # Returns
+///
+/// The content with links relative to the cache directory (e.g.
+/// `assets/0123abcd.css`) for cached assets, and absolute links for the others.
+pub fn link_cached_assets(cache: &PageCache, content: &str, page_url: &str) -> String {
+    let mut local = HashMap::new();
+    for url in asset_urls(content) {
+        let Some(absolute) = resolve(page_url, &url) else {
+            continue;
+        };
+        let replacement = match cache.asset(&absolute) {
+            Some(file) => format!("{}/{}", ASSETS_DIR_NAME, file),
+            None => absolute,
+        };
+        local.insert(url, replacement);
+    }
+    rewrite_asset_urls(content, |url| local.get(url).cloned())
+}
+
+/// Resolve a link against the URL of the document containing it
+///
+/// # Returns
+///
+/// The absolute URL without its fragment, or `None` if it is not an HTTP(S) URL.
+fn resolve(base: &str, link: &str) -> Option<String> {
+    let mut url = reqwest::Url::parse(base).ok()?.join(link.trim()).ok()?;
+    url.set_fragment(None);
+    matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let page = "https://cppreference.cn/w/cpp/algorithm/sort";
+        assert_eq!(
+            resolve(page, "/mwiki/load.php?modules=site&only=styles").as_deref(),
+            Some("https://cppreference.cn/mwiki/load.php?modules=site&only=styles")
+        );
+        assert_eq!(
+            resolve(page, "//upload.cppreference.com/a.png#x").as_deref(),
+            Some("https://upload.cppreference.com/a.png")
+        );
+        assert_eq!(
+            resolve(page, "../../fonts/a.woff2").as_deref(),
+            Some("https://cppreference.cn/w/fonts/a.woff2")
+        );
+        assert_eq!(resolve(page, "javascript:void(0)"), None);
+    }
+}
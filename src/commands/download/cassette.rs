@@ -0,0 +1,230 @@
+//! Recording and replaying HTTP exchanges
+//!
+//! A cassette is a directory holding one JSON file per requested URL, named
+//! after the SHA-256 hash of the URL. Each file records the request (URL and
+//! headers) and the response (status, final URL after redirects, headers and
+//! body) of the last exchange with that URL, except that a `304 Not Modified`
+//! answer never replaces a recorded exchange, so that a cassette recorded while
+//! refreshing a cache still holds the pages. Recording a download and
+//! replaying it later reproduces the same pages without network access, on
+//! any machine.
+//!
+//! Cookie values are never written to a cassette, so captures can be shared.
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{cache::sha256_hex, errors::AppError};
+
+/// Headers whose values are replaced with [`REDACTED`] when recording
+const SECRET_HEADERS: [&str; 3] = ["cookie", "set-cookie", "authorization"];
+
+/// The value recorded for secret headers
+pub const REDACTED: &str = "<redacted>";
+
+/// Whether a download records or replays a cassette
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Send requests over the network and save every exchange in the directory
+    Record(PathBuf),
+    /// Answer requests from the exchanges saved in the directory
+    Replay(PathBuf),
+}
+
+/// A recorded HTTP exchange
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Exchange {
+    /// The requested URL
+    pub url: String,
+    /// The headers sent with the request, besides the defaults of the client
+    pub request_headers: Vec<(String, String)>,
+    /// The status of the response
+    pub status: u16,
+    /// The URL the response was served from, after redirects
+    pub final_url: String,
+    /// The headers of the response
+    pub response_headers: Vec<(String, String)>,
+    /// The body of the response, as text or base64
+    pub body: String,
+    /// Whether `body` is base64 because the body is not valid UTF-8
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub base64: bool,
+}
+
+impl Exchange {
+    /// Record an exchange, redacting secret headers
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The requested URL
+    /// * `request_headers` - The headers sent with the request
+    /// * `status` - The status of the response
+    /// * `final_url` - The URL the response was served from
+    /// * `response_headers` - The headers of the response
+    /// * `body` - The body of the response
+    pub fn new(
+        url: &str,
+        request_headers: Vec<(String, String)>,
+        status: u16,
+        final_url: &str,
+        response_headers: Vec<(String, String)>,
+        body: &[u8],
+    ) -> Self {
+        let (body, base64) = match std::str::from_utf8(body) {
+            Ok(text) => (text.to_string(), false),
+            Err(_) => (base64::engine::general_purpose::STANDARD.encode(body), true),
+        };
+        Exchange {
+            url: url.to_string(),
+            request_headers: redact(request_headers),
+            status,
+            final_url: final_url.to_string(),
+            response_headers: redact(response_headers),
+            body,
+            base64,
+        }
+    }
+
+    /// The body of the response as bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::InvalidCassette`] if a base64 body is malformed.
+    pub fn body_bytes(&self, path: &Path) -> Result<Vec<u8>, AppError> {
+        if !self.base64 {
+            return Ok(self.body.clone().into_bytes());
+        }
+        base64::engine::general_purpose::STANDARD
+            .decode(&self.body)
+            .map_err(|e| AppError::InvalidCassette {
+                file: path.display().to_string(),
+                reason: format!("malformed base64 body: {}", e),
+            })
+    }
+}
+
+/// A directory of recorded exchanges
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    /// The directory holding the exchanges
+    dir: PathBuf,
+}
+
+impl Cassette {
+    /// Use a directory as a cassette
+    pub fn new(dir: &Path) -> Self {
+        Cassette {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// The file the exchange with a URL is saved in
+    pub fn path(&self, url: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", sha256_hex(url.as_bytes())))
+    }
+
+    /// Whether an exchange with a URL was saved
+    pub fn contains(&self, url: &str) -> bool {
+        self.path(url).is_file()
+    }
+
+    /// Save an exchange, replacing an earlier exchange with the same URL
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or the file cannot be written.
+    pub fn save(&self, exchange: &Exchange) -> Result<(), AppError> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(exchange)?;
+        fs::write(self.path(&exchange.url), json + "\n")?;
+        Ok(())
+    }
+
+    /// Load the exchange with a URL
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::NotRecorded`] if no exchange with the URL was
+    /// saved, or [`AppError::InvalidCassette`] if its file is malformed.
+    pub fn load(&self, url: &str) -> Result<(Exchange, PathBuf), AppError> {
+        let path = self.path(url);
+        let json = match fs::read_to_string(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(AppError::NotRecorded {
+                    url: url.to_string(),
+                    dir: self.dir.display().to_string(),
+                });
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let exchange: Exchange =
+            serde_json::from_str(&json).map_err(|e| AppError::InvalidCassette {
+                file: path.display().to_string(),
+                reason: e.to_string(),
+            })?;
+        if exchange.url != url {
+            return Err(AppError::InvalidCassette {
+                file: path.display().to_string(),
+                reason: format!("recorded for {} instead of {}", exchange.url, url),
+            });
+        }
+        Ok((exchange, path))
+    }
+}
+
+/// Replace the values of secret headers with [`REDACTED`]
+fn redact(headers: Vec<(String, String)>) -> Vec<(String, String)> {
+    headers
+        .into_iter()
+        .map(|(name, value)| {
+            if SECRET_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+                (name, REDACTED.to_string())
+            } else {
+                (name, value)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cassette = Cassette::new(&temp_dir.path().join("cassette"));
+        let url = "https://cppreference.cn/w/cpp/algorithm/sort";
+        assert!(matches!(
+            cassette.load(url),
+            Err(AppError::NotRecorded { .. })
+        ));
+
+        let page = Exchange::new(
+            url,
+            vec![("cookie".to_string(), "session=secret".to_string())],
+            200,
+            url,
+            vec![("etag".to_string(), "\"1\"".to_string())],
+            "<html>排序</html>".as_bytes(),
+        );
+        assert_eq!(page.request_headers[0].1, REDACTED);
+        cassette.save(&page).unwrap();
+        let (loaded, path) = cassette.load(url).unwrap();
+        assert_eq!(loaded, page);
+        assert!(!fs::read_to_string(&path).unwrap().contains("secret"));
+
+        let image_url = "https://cppreference.cn/logo.png";
+        let image = Exchange::new(image_url, vec![], 200, image_url, vec![], &[0x89, 0xff]);
+        assert!(image.base64);
+        cassette.save(&image).unwrap();
+        let (loaded, path) = cassette.load(image_url).unwrap();
+        assert_eq!(loaded.body_bytes(&path).unwrap(), [0x89, 0xff]);
+    }
+}
@@ -0,0 +1,558 @@
+//! Fetching pages over HTTP
+//!
+//! Every request goes through the rate limiter and its response status is
+//! checked, so that error pages are never stored as references. Transient
+//! failures (timeouts, connection errors, `429 Too Many Requests` and server
+//! errors) are retried with exponential backoff. Requests can carry the
+//! validators of a cached copy, so that unchanged pages are not sent again.
+//! Redirects are checked against a [`RedirectPolicy`]. Assets such as
+//! stylesheets and images are fetched the same way, but as raw bytes.
+//!
+//! Requests are sent through an [`HttpClient`], which can also record every
+//! exchange in a cassette or answer requests from one (see [`super::cassette`]).
+
+use log::warn;
+use reqwest::{
+    StatusCode,
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+};
+use serde::Deserialize;
+use std::{future::Future, str::FromStr};
+use tokio::time::Duration;
+
+use super::{
+    cassette::{Cassette, CassetteMode, Exchange},
+    profile::CookieJar,
+    rate_limit::RateLimiter,
+};
+use crate::{errors::AppError, references::normalize_url};
+
+/// Which redirects are accepted
+///
+/// URLs are compared with [`normalize_url`], so a redirect that only adds or
+/// removes a `.html` suffix or a trailing slash is not a redirect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedirectPolicy {
+    /// Fail on every redirect
+    Forbid,
+    /// Accept redirects to the same host, e.g. from an old path to a new one,
+    /// but fail on redirects to another host, e.g. from the Chinese site to
+    /// the English one
+    #[default]
+    SameHost,
+    /// Accept every redirect
+    Follow,
+}
+
+impl RedirectPolicy {
+    /// Check the URL a page was served from against the policy
+    ///
+    /// # Returns
+    ///
+    /// `true` if the page was redirected and the redirect is accepted,
+    /// `false` if it was not redirected.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::Redirected`] if the redirect is not accepted.
+    pub fn check(self, url: &str, final_url: &str) -> Result<bool, AppError> {
+        if normalize_url(url) == normalize_url(final_url) {
+            return Ok(false);
+        }
+
+        let host = |url: &str| {
+            reqwest::Url::parse(url)
+                .ok()
+                .and_then(|url| url.host_str().map(str::to_string))
+        };
+        let accepted = match self {
+            RedirectPolicy::Forbid => false,
+            RedirectPolicy::SameHost => host(url).is_some() && host(url) == host(final_url),
+            RedirectPolicy::Follow => true,
+        };
+        if accepted {
+            Ok(true)
+        } else {
+            Err(AppError::Redirected {
+                url: url.to_string(),
+                final_url: final_url.to_string(),
+            })
+        }
+    }
+}
+
+impl FromStr for RedirectPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forbid" => Ok(RedirectPolicy::Forbid),
+            "same-host" => Ok(RedirectPolicy::SameHost),
+            "follow" => Ok(RedirectPolicy::Follow),
+            _ => Err(format!(
+                "invalid redirect policy '{}', expected 'forbid', 'same-host' or 'follow'",
+                s
+            )),
+        }
+    }
+}
+
+/// Where requests are sent
+#[derive(Debug, Clone)]
+pub enum HttpClient {
+    /// Requests go over the network
+    Live(reqwest::Client),
+    /// Requests go over the network and every exchange is saved in a cassette
+    Recording(reqwest::Client, Cassette),
+    /// Requests are answered from a cassette, without network access
+    Replaying(Cassette),
+}
+
+impl HttpClient {
+    /// Wrap a client according to the cassette mode
+    pub fn new(client: reqwest::Client, cassette: Option<&CassetteMode>) -> Self {
+        match cassette {
+            None => HttpClient::Live(client),
+            Some(CassetteMode::Record(dir)) => HttpClient::Recording(client, Cassette::new(dir)),
+            Some(CassetteMode::Replay(dir)) => HttpClient::Replaying(Cassette::new(dir)),
+        }
+    }
+
+    /// Send a `GET` request and read the whole response
+    ///
+    /// The status of the response is not checked.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to request
+    /// * `headers` - Headers sent besides the defaults of the client
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::HttpError`] if the request fails, an error if the
+    /// exchange cannot be recorded, or [`AppError::NotRecorded`] if a
+    /// replayed cassette has no exchange with `url`.
+    pub async fn get(&self, url: &str, headers: HeaderMap) -> Result<Response, AppError> {
+        match self {
+            HttpClient::Live(client) => Response::fetch(client, url, headers).await,
+            HttpClient::Recording(client, cassette) => {
+                let response = Response::fetch(client, url, headers.clone()).await?;
+                // A `304 Not Modified` is only meaningful with the cached copy
+                // it answers, so it never replaces a recorded page
+                if response.status != StatusCode::NOT_MODIFIED || !cassette.contains(url) {
+                    cassette.save(&Exchange::new(
+                        url,
+                        header_pairs(&headers),
+                        response.status,
+                        &response.final_url,
+                        header_pairs(&response.headers),
+                        &response.body,
+                    ))?;
+                }
+                Ok(response)
+            }
+            HttpClient::Replaying(cassette) => {
+                let (exchange, path) = cassette.load(url)?;
+                let mut headers = HeaderMap::new();
+                for (name, value) in &exchange.response_headers {
+                    if let (Ok(name), Ok(value)) = (
+                        HeaderName::from_bytes(name.as_bytes()),
+                        HeaderValue::from_str(value),
+                    ) {
+                        headers.append(name, value);
+                    }
+                }
+                Ok(Response {
+                    status: exchange.status,
+                    final_url: exchange.final_url.clone(),
+                    headers,
+                    body: exchange.body_bytes(&path)?,
+                })
+            }
+        }
+    }
+}
+
+/// A response with its body read
+#[derive(Debug, Clone)]
+pub struct Response {
+    /// The status code
+    pub status: u16,
+    /// The URL the response was served from, after redirects
+    pub final_url: String,
+    /// The response headers
+    pub headers: HeaderMap,
+    /// The body
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Send a request over the network and read the response
+    async fn fetch(
+        client: &reqwest::Client,
+        url: &str,
+        headers: HeaderMap,
+    ) -> Result<Self, AppError> {
+        let response = client.get(url).headers(headers).send().await?;
+        let status = response.status().as_u16();
+        let final_url = response.url().to_string();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?.to_vec();
+        Ok(Response {
+            status,
+            final_url,
+            headers,
+            body,
+        })
+    }
+
+    /// The value of a header, if it is present and valid text
+    pub fn header(&self, name: HeaderName) -> Option<String> {
+        self.headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    }
+}
+
+/// How often and how patiently failed requests are retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub retries: u32,
+    /// Delay before the first retry, doubled for every further retry
+    pub backoff: Duration,
+}
+
+impl RetryPolicy {
+    /// The delay before a retry
+    ///
+    /// # Arguments
+    ///
+    /// * `retry` - The number of the retry, starting at 0
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.backoff.saturating_mul(2u32.saturating_pow(retry))
+    }
+}
+
+/// Validators of a cached copy of a page
+///
+/// If any is set, the request is conditional and the server may answer that
+/// the page has not been modified.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validators {
+    /// Sent as `If-None-Match`
+    pub etag: Option<String>,
+    /// Sent as `If-Modified-Since`
+    pub last_modified: Option<String>,
+}
+
+/// A successfully fetched page
+#[derive(Debug, Clone)]
+pub struct FetchedPage {
+    /// The URL that was requested
+    pub url: String,
+    /// The URL the page was served from, after redirects
+    pub final_url: String,
+    /// The `ETag` header of the response
+    pub etag: Option<String>,
+    /// The `Last-Modified` header of the response
+    pub last_modified: Option<String>,
+    /// The body of the response
+    pub body: String,
+}
+
+/// A successfully fetched asset
+#[derive(Debug, Clone)]
+pub struct FetchedAsset {
+    /// The `Content-Type` header of the response
+    pub content_type: Option<String>,
+    /// The body of the response
+    pub body: Vec<u8>,
+}
+
+/// Fetch a page, retrying transient failures
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client
+/// * `limiter` - The rate limiter shared by all downloads
+/// * `cookies` - The cookies to send
+/// * `url` - The URL to fetch
+/// * `validators` - Validators of a cached copy, making the request conditional
+/// * `policy` - The retry policy
+///
+/// # Returns
+///
+/// The page, or `None` if the server answered `304 Not Modified`.
+///
+/// # Errors
+///
+/// Returns [`AppError::HttpStatus`] if the server answers with an
+/// unsuccessful status, or [`AppError::HttpError`] if the request fails,
+/// once all retries are used up or immediately if the failure is permanent.
+pub async fn fetch_page(
+    client: &HttpClient,
+    limiter: &RateLimiter,
+    cookies: &CookieJar,
+    url: &str,
+    validators: &Validators,
+    policy: RetryPolicy,
+) -> Result<Option<FetchedPage>, AppError> {
+    with_retries(limiter, url, policy, || {
+        fetch_once(client, cookies, url, validators)
+    })
+    .await
+}
+
+/// Fetch an asset, retrying transient failures
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client
+/// * `limiter` - The rate limiter shared by all downloads
+/// * `cookies` - The cookies to send
+/// * `url` - The URL to fetch
+/// * `policy` - The retry policy
+///
+/// # Errors
+///
+/// Returns [`AppError::HttpStatus`] if the server answers with an
+/// unsuccessful status, or [`AppError::HttpError`] if the request fails,
+/// once all retries are used up or immediately if the failure is permanent.
+pub async fn fetch_asset(
+    client: &HttpClient,
+    limiter: &RateLimiter,
+    cookies: &CookieJar,
+    url: &str,
+    policy: RetryPolicy,
+) -> Result<FetchedAsset, AppError> {
+    with_retries(limiter, url, policy, || async {
+        let response = send(client, HeaderMap::new(), cookies, url).await?;
+        Ok(FetchedAsset {
+            content_type: response.header(header::CONTENT_TYPE),
+            body: response.body,
+        })
+    })
+    .await
+}
+
+/// Run a request through the rate limiter until it succeeds or fails permanently
+async fn with_retries<T, F, Fut>(
+    limiter: &RateLimiter,
+    url: &str,
+    policy: RetryPolicy,
+    mut attempt: F,
+) -> Result<T, AppError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AppError>>,
+{
+    let mut retry = 0;
+    loop {
+        limiter.acquire(url).await;
+        match attempt().await {
+            Err(error) if retry < policy.retries && is_transient(&error) => {
+                let delay = policy.delay(retry);
+                warn!(
+                    "{} (retry {} of {} in {:?})",
+                    error,
+                    retry + 1,
+                    policy.retries,
+                    delay
+                );
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Send a single request and check its status
+async fn fetch_once(
+    client: &HttpClient,
+    cookies: &CookieJar,
+    url: &str,
+    validators: &Validators,
+) -> Result<Option<FetchedPage>, AppError> {
+    let mut headers = HeaderMap::new();
+    let conditions = [
+        (header::IF_NONE_MATCH, &validators.etag),
+        (header::IF_MODIFIED_SINCE, &validators.last_modified),
+    ];
+    for (name, value) in conditions {
+        if let Some(value) = value.as_deref().and_then(|value| value.parse().ok()) {
+            headers.insert(name, value);
+        }
+    }
+
+    let response = match send(client, headers, cookies, url).await {
+        Err(AppError::HttpStatus { status, .. }) if status == StatusCode::NOT_MODIFIED => {
+            return Ok(None);
+        }
+        result => result?,
+    };
+
+    Ok(Some(FetchedPage {
+        url: url.to_string(),
+        etag: response.header(header::ETAG),
+        last_modified: response.header(header::LAST_MODIFIED),
+        body: String::from_utf8_lossy(&response.body).into_owned(),
+        final_url: response.final_url,
+    }))
+}
+
+/// Send a request with the cookies for `url` and check its status
+///
+/// # Errors
+///
+/// Returns [`AppError::HttpStatus`] if the status is not successful,
+/// including `304 Not Modified`.
+async fn send(
+    client: &HttpClient,
+    mut headers: HeaderMap,
+    cookies: &CookieJar,
+    url: &str,
+) -> Result<Response, AppError> {
+    if let Some(cookie) = cookies
+        .header_for(url)
+        .and_then(|cookie| cookie.parse().ok())
+    {
+        headers.insert(header::COOKIE, cookie);
+    }
+    let response = client.get(url, headers).await?;
+    if !StatusCode::from_u16(response.status).is_ok_and(|status| status.is_success()) {
+        return Err(AppError::HttpStatus {
+            url: url.to_string(),
+            status: response.status,
+        });
+    }
+    Ok(response)
+}
+
+/// The headers of a request or response as name-value pairs
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).into_owned(),
+            )
+        })
+        .collect()
+}
+
+/// Whether a failed request may succeed when retried
+pub fn is_transient(error: &AppError) -> bool {
+    match error {
+        AppError::HttpStatus { status, .. } => StatusCode::from_u16(*status).is_ok_and(|status| {
+            status == StatusCode::REQUEST_TIMEOUT
+                || status == StatusCode::TOO_MANY_REQUESTS
+                || status.is_server_error()
+        }),
+        AppError::HttpError(error) => error.is_timeout() || error.is_connect() || error.is_body(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::download::test_server::{Route, TestServer};
+
+    #[test]
+    fn test_delay_doubles() {
+        let policy = RetryPolicy {
+            retries: 3,
+            backoff: Duration::from_millis(500),
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(500));
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_redirect_policy() {
+        let url = "https://cppreference.cn/w/cpp/container/vector";
+        let moved = "https://cppreference.cn/w/cpp/container/vector/vector";
+        let english = "https://en.cppreference.com/w/cpp/container/vector.html";
+
+        assert!(!RedirectPolicy::Forbid.check(url, url).unwrap());
+        assert!(
+            !RedirectPolicy::Forbid
+                .check(url, &format!("{}/", url))
+                .unwrap()
+        );
+        assert!(matches!(
+            RedirectPolicy::Forbid.check(url, moved),
+            Err(AppError::Redirected { .. })
+        ));
+        assert!(RedirectPolicy::SameHost.check(url, moved).unwrap());
+        assert!(matches!(
+            RedirectPolicy::SameHost.check(url, english),
+            Err(AppError::Redirected { .. })
+        ));
+        assert!(RedirectPolicy::Follow.check(url, english).unwrap());
+        assert_eq!(
+            "same-host".parse::<RedirectPolicy>(),
+            Ok(RedirectPolicy::SameHost)
+        );
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let status = |status| AppError::HttpStatus {
+            url: "https://en.cppreference.com/w/cpp/foo.html".to_string(),
+            status,
+        };
+        assert!(is_transient(&status(503)));
+        assert!(is_transient(&status(429)));
+        assert!(!is_transient(&status(404)));
+        assert!(!is_transient(&status(403)));
+        assert!(!is_transient(&AppError::LintFailed {
+            errors: 1,
+            warnings: 0
+        }));
+    }
+
+    #[test]
+    fn test_assets_on_other_hosts_get_no_cookies() {
+        let server = TestServer::start([("/logo.png".to_string(), Route::Page("png".to_string()))]);
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cassette = Cassette::new(temp_dir.path());
+        let client = HttpClient::Recording(reqwest::Client::new(), cassette.clone());
+        let limiter = RateLimiter::new(0.0, Duration::ZERO).unwrap();
+        let policy = RetryPolicy {
+            retries: 0,
+            backoff: Duration::ZERO,
+        };
+        let mut cookies = CookieJar::default();
+        cookies.add_header("session=secret", "127.0.0.1");
+
+        // The same server under another host name stands in for a third-party host
+        let same_host = format!("{}/logo.png", server.url());
+        let other_host = same_host.replace("127.0.0.1", "localhost");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        for url in [&same_host, &other_host] {
+            runtime
+                .block_on(fetch_asset(&client, &limiter, &cookies, url, policy))
+                .unwrap();
+        }
+
+        let sent_cookie = |url: &str| {
+            let (exchange, _) = cassette.load(url).unwrap();
+            exchange
+                .request_headers
+                .iter()
+                .any(|(name, _)| name == "cookie")
+        };
+        assert!(sent_cookie(&same_host));
+        assert!(!sent_cookie(&other_host));
+    }
+}
@@ -0,0 +1,369 @@
+//! Per-language request profiles
+//!
+//! Some editions of cppreference need extra request headers or browser cookies
+//! (e.g. to keep cppreference.cn from redirecting to the English site). A
+//! [`RequestProfile`] holds these for one language and is configured in the
+//! `[profiles.<lang>]` table of `algcmp.toml`:
+//!
+//! ```toml
+//! [profiles.zh]
+//! user_agent = "Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0"
+//! cookies_file = "cookies.txt"
+//!
+//! [profiles.zh.headers]
+//! Accept-Language = "zh-CN,zh-Hans;q=0.9"
+//! ```
+//!
+//! Cookies are read from a Netscape `cookies.txt` file, as exported by browser
+//! extensions and `curl -c`, and from an environment variable holding a
+//! `Cookie` header value (`ALGCMP_COOKIE_ZH` for `zh` unless `cookie_env` says
+//! otherwise), so that secrets need not be written to the configuration.
+
+use log::debug;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::errors::AppError;
+
+/// User agent sent unless a profile sets another one
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Safari/537.36";
+
+/// Request settings for one language
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RequestProfile {
+    /// User agent, instead of [`DEFAULT_USER_AGENT`]
+    pub user_agent: Option<String>,
+    /// Extra headers sent with every request
+    pub headers: BTreeMap<String, String>,
+    /// Netscape `cookies.txt` file to load cookies from
+    pub cookies_file: Option<PathBuf>,
+    /// Environment variable holding a `Cookie` header value
+    /// (default: `ALGCMP_COOKIE_<LANG>`)
+    pub cookie_env: Option<String>,
+}
+
+impl RequestProfile {
+    /// The environment variable cookies are read from for a language
+    pub fn cookie_env(&self, lang: &str) -> String {
+        self.cookie_env.clone().unwrap_or_else(|| {
+            format!(
+                "ALGCMP_COOKIE_{}",
+                lang.to_ascii_uppercase().replace('-', "_")
+            )
+        })
+    }
+
+    /// Build an HTTP client sending the user agent and headers of the profile
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Time after which connecting or a whole request is abandoned
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::ConfigError`] if a header is invalid, or an error if
+    /// the client cannot be created.
+    pub fn client(&self, timeout: Duration) -> Result<reqwest::Client, AppError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let invalid = |reason: String| AppError::ConfigError {
+                file: "request profile".to_string(),
+                reason: format!("invalid header {}: {}", name, reason),
+            };
+            let header_name =
+                HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(e.to_string()))?;
+            let header_value = HeaderValue::from_str(value).map_err(|e| invalid(e.to_string()))?;
+            headers.insert(header_name, header_value);
+        }
+
+        Ok(reqwest::Client::builder()
+            .user_agent(self.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT))
+            .default_headers(headers)
+            .connect_timeout(timeout)
+            .timeout(timeout)
+            .build()?)
+    }
+
+    /// Load the cookies of the profile
+    ///
+    /// Cookies from the environment variable carry no domain, so they are
+    /// scoped to `host`; otherwise they would also be sent to the other hosts
+    /// that pages link assets from.
+    ///
+    /// # Arguments
+    ///
+    /// * `lang` - The language, naming the default environment variable
+    /// * `host` - The host the pages are downloaded from
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cookies file cannot be read or is malformed.
+    pub fn cookie_jar(&self, lang: &str, host: &str) -> Result<CookieJar, AppError> {
+        let mut jar = match &self.cookies_file {
+            Some(path) => CookieJar::load(path)?,
+            None => CookieJar::default(),
+        };
+        let env = self.cookie_env(lang);
+        if let Ok(header) = std::env::var(&env) {
+            debug!("Using cookies from ${}", env);
+            jar.add_header(&header, host);
+        }
+        Ok(jar)
+    }
+}
+
+/// A cookie sent with requests
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    /// The domain the cookie belongs to, or `None` for every host
+    pub domain: Option<String>,
+    /// Whether the cookie is also sent to subdomains of `domain`
+    pub include_subdomains: bool,
+    /// The path prefix the cookie is sent for
+    pub path: String,
+    /// Whether the cookie is only sent over HTTPS
+    pub secure: bool,
+    /// The name of the cookie
+    pub name: String,
+    /// The value of the cookie
+    pub value: String,
+}
+
+impl Cookie {
+    /// Whether the cookie is sent with a request to `url`
+    fn matches(&self, url: &reqwest::Url) -> bool {
+        if self.secure && url.scheme() != "https" {
+            return false;
+        }
+        if !path_matches(url.path(), &self.path) {
+            return false;
+        }
+        let Some(domain) = &self.domain else {
+            return true;
+        };
+        let domain = domain.trim_start_matches('.');
+        let host = url.host_str().unwrap_or_default();
+        host.eq_ignore_ascii_case(domain)
+            || (self.include_subdomains
+                && host.len() > domain.len()
+                && host.to_ascii_lowercase().ends_with(&format!(".{}", domain)))
+    }
+}
+
+/// Whether a cookie with path `cookie_path` is sent for `request_path`
+///
+/// As in RFC 6265, section 5.1.4, the paths must be equal or the cookie path
+/// must end at a `/` of the request path, so that a cookie for `/w` is sent
+/// for `/w/cpp` but not for `/wiki`.
+fn path_matches(request_path: &str, cookie_path: &str) -> bool {
+    match request_path.strip_prefix(cookie_path) {
+        Some(rest) => rest.is_empty() || cookie_path.ends_with('/') || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+/// The cookies sent with requests
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CookieJar {
+    /// The cookies, in the order they were loaded
+    pub cookies: Vec<Cookie>,
+}
+
+impl CookieJar {
+    /// Load a Netscape `cookies.txt` file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or
+    /// [`AppError::ConfigError`] if a line is malformed.
+    pub fn load(path: &Path) -> Result<CookieJar, AppError> {
+        let content = fs::read_to_string(path)?;
+        CookieJar::parse(&content).map_err(|reason| AppError::ConfigError {
+            file: path.display().to_string(),
+            reason,
+        })
+    }
+
+    /// Parse the contents of a Netscape `cookies.txt` file
+    ///
+    /// Every line holds seven tab-separated fields: domain, whether subdomains
+    /// are included, path, whether the cookie is secure, expiry as a Unix
+    /// timestamp (0 for session cookies), name and value. Lines starting with
+    /// `#` are comments, except for the `#HttpOnly_` prefix of HTTP-only
+    /// cookies. Expired cookies are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first malformed line.
+    pub fn parse(content: &str) -> Result<CookieJar, String> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        let mut jar = CookieJar::default();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [
+                domain,
+                include_subdomains,
+                path,
+                secure,
+                expires,
+                name,
+                value,
+            ] = fields[..]
+            else {
+                return Err(format!(
+                    "line {}: expected 7 tab-separated fields, found {}",
+                    index + 1,
+                    fields.len()
+                ));
+            };
+            let flag = |field: &str| match field {
+                "TRUE" => Ok(true),
+                "FALSE" => Ok(false),
+                _ => Err(format!(
+                    "line {}: expected TRUE or FALSE, found {:?}",
+                    index + 1,
+                    field
+                )),
+            };
+            let expires: u64 = expires
+                .parse()
+                .map_err(|_| format!("line {}: invalid expiry {:?}", index + 1, expires))?;
+            if expires != 0 && expires < now {
+                debug!("Ignoring expired cookie {} for {}", name, domain);
+                continue;
+            }
+
+            jar.cookies.push(Cookie {
+                domain: Some(domain.to_string()),
+                include_subdomains: flag(include_subdomains)?,
+                path: path.to_string(),
+                secure: flag(secure)?,
+                name: name.to_string(),
+                value: value.to_string(),
+            });
+        }
+        Ok(jar)
+    }
+
+    /// Add the cookies of a `Cookie` header value, sent to `domain` only
+    pub fn add_header(&mut self, header: &str, domain: &str) {
+        for pair in header.split(';') {
+            if let Some((name, value)) = pair.split_once('=') {
+                self.cookies.push(Cookie {
+                    domain: Some(domain.to_string()),
+                    include_subdomains: false,
+                    path: "/".to_string(),
+                    secure: false,
+                    name: name.trim().to_string(),
+                    value: value.trim().to_string(),
+                });
+            }
+        }
+    }
+
+    /// The `Cookie` header value for a request to `url`
+    ///
+    /// # Returns
+    ///
+    /// The header value, or `None` if no cookie matches.
+    pub fn header_for(&self, url: &str) -> Option<String> {
+        let url = reqwest::Url::parse(url).ok()?;
+        let pairs: Vec<String> = self
+            .cookies
+            .iter()
+            .filter(|cookie| cookie.matches(&url))
+            .map(|cookie| format!("{}={}", cookie.name, cookie.value))
+            .collect();
+        (!pairs.is_empty()).then(|| pairs.join("; "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cookies_txt() {
+        let content = "# Netscape HTTP Cookie File\n\
+                       \n\
+                       .cppreference.cn\tTRUE\t/\tTRUE\t0\tsession\tabc\n\
+                       #HttpOnly_cppreference.cn\tFALSE\t/w/\tFALSE\t4102444800\ttoken\txyz\n\
+                       example.com\tFALSE\t/\tFALSE\t1\texpired\t1\n";
+        let mut jar = CookieJar::parse(content).unwrap();
+        assert_eq!(jar.cookies.len(), 2);
+
+        assert_eq!(
+            jar.header_for("https://cppreference.cn/w/cpp/algorithm/sort")
+                .as_deref(),
+            Some("session=abc; token=xyz")
+        );
+        assert_eq!(
+            jar.header_for("https://www.cppreference.cn/").as_deref(),
+            Some("session=abc")
+        );
+        assert_eq!(jar.header_for("http://cppreference.cn/x"), None);
+        assert_eq!(jar.header_for("https://en.cppreference.com/w/cpp"), None);
+
+        jar.add_header("a=1; b=2", "en.cppreference.com");
+        assert_eq!(
+            jar.header_for("https://en.cppreference.com/w/cpp")
+                .as_deref(),
+            Some("a=1; b=2")
+        );
+        assert_eq!(
+            jar.header_for("https://upload.cppreference.com/a.png"),
+            None
+        );
+
+        let scoped = CookieJar::parse("cppreference.cn\tFALSE\t/w\tFALSE\t0\tw\t1\n").unwrap();
+        for (url, sent) in [
+            ("https://cppreference.cn/w", true),
+            ("https://cppreference.cn/w/cpp", true),
+            ("https://cppreference.cn/wiki/Main_Page", false),
+            ("https://cppreference.cn/", false),
+        ] {
+            assert_eq!(scoped.header_for(url).is_some(), sent, "{}", url);
+        }
+
+        let error = CookieJar::parse("cppreference.cn\tTRUE\t/\n").unwrap_err();
+        assert!(error.starts_with("line 1: expected 7"), "{}", error);
+    }
+
+    #[test]
+    fn test_profile_client_and_env() {
+        let profile = RequestProfile {
+            headers: BTreeMap::from([(
+                "Accept-Language".to_string(),
+                "zh-CN,zh-Hans;q=0.9".to_string(),
+            )]),
+            ..RequestProfile::default()
+        };
+        assert!(profile.client(Duration::from_secs(30)).is_ok());
+        assert_eq!(profile.cookie_env("zh"), "ALGCMP_COOKIE_ZH");
+
+        let invalid = RequestProfile {
+            headers: BTreeMap::from([("Bad Header".to_string(), "x".to_string())]),
+            ..RequestProfile::default()
+        };
+        assert!(matches!(
+            invalid.client(Duration::from_secs(30)),
+            Err(AppError::ConfigError { .. })
+        ));
+    }
+}
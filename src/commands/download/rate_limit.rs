@@ -0,0 +1,134 @@
+//! Per-host rate limiting for downloads
+//!
+//! Pages are downloaded concurrently, but every host only gets a fixed number
+//! of requests per second. [`RateLimiter::acquire`] hands out evenly spaced
+//! slots per host and delays each request by a random jitter on top, so
+//! concurrent downloads do not reach the server in bursts.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+};
+use tokio::time::{Duration, Instant};
+
+use crate::errors::AppError;
+
+/// Spaces out requests to the same host
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// Time between two requests to the same host
+    interval: Duration,
+    /// Upper bound of the random delay added to every request
+    jitter: Duration,
+    /// The next free slot of every host
+    next_slots: Mutex<HashMap<String, Instant>>,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter
+    ///
+    /// # Arguments
+    ///
+    /// * `requests_per_second` - The budget of every host; zero or less disables the limit
+    /// * `jitter` - Upper bound of the random delay added to every request
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::InvalidRequestRate`] if the rate is so small that
+    /// the time between two requests overflows a [`Duration`].
+    pub fn new(requests_per_second: f64, jitter: Duration) -> Result<Self, AppError> {
+        let interval = if requests_per_second > 0.0 {
+            Duration::try_from_secs_f64(1.0 / requests_per_second).map_err(|_| {
+                AppError::InvalidRequestRate {
+                    rate: requests_per_second,
+                }
+            })?
+        } else {
+            Duration::ZERO
+        };
+        Ok(RateLimiter {
+            interval,
+            jitter,
+            next_slots: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Wait until a request to the host of `url` may be sent
+    pub async fn acquire(&self, url: &str) {
+        let slot = self.reserve(&host_of(url), Instant::now());
+        let jitter = Duration::from_micros(fastrand::u64(0..=self.jitter.as_micros() as u64));
+        tokio::time::sleep_until(slot + jitter).await;
+    }
+
+    /// Reserve the next slot of a host
+    ///
+    /// # Returns
+    ///
+    /// The time the request may be sent at, ignoring jitter. This is `now` if
+    /// the host has not been used for at least one interval.
+    fn reserve(&self, host: &str, now: Instant) -> Instant {
+        let mut next_slots = self
+            .next_slots
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let slot = next_slots
+            .get(host)
+            .map_or(now, |&next_slot| next_slot.max(now));
+        next_slots.insert(host.to_string(), slot + self.interval);
+        slot
+    }
+}
+
+/// The host part of a URL, or the whole URL if it cannot be parsed
+fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .unwrap_or_else(|| url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_spaces_requests_per_host() {
+        let limiter = RateLimiter::new(4.0, Duration::ZERO).unwrap();
+        let now = Instant::now();
+        let interval = Duration::from_millis(250);
+
+        assert_eq!(limiter.reserve("en.cppreference.com", now), now);
+        assert_eq!(limiter.reserve("en.cppreference.com", now), now + interval);
+        assert_eq!(
+            limiter.reserve("en.cppreference.com", now),
+            now + interval * 2
+        );
+        assert_eq!(limiter.reserve("cppreference.cn", now), now);
+
+        let later = now + interval * 10;
+        assert_eq!(limiter.reserve("en.cppreference.com", later), later);
+    }
+
+    #[test]
+    fn test_unlimited_and_host_of() {
+        let limiter = RateLimiter::new(0.0, Duration::ZERO).unwrap();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve("en.cppreference.com", now), now);
+        assert_eq!(limiter.reserve("en.cppreference.com", now), now);
+
+        assert_eq!(
+            host_of("https://en.cppreference.com/w/cpp/algorithm/sort.html"),
+            "en.cppreference.com"
+        );
+        assert_eq!(host_of("not a url"), "not a url");
+    }
+
+    #[test]
+    fn test_rate_too_small() {
+        assert!(matches!(
+            RateLimiter::new(1e-20, Duration::ZERO),
+            Err(AppError::InvalidRequestRate { .. })
+        ));
+        assert!(RateLimiter::new(1e-6, Duration::ZERO).is_ok());
+    }
+}
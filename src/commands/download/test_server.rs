@@ -0,0 +1,263 @@
+//! A local stand-in for cppreference, for tests
+//!
+//! [`TestServer`] serves fixture pages, redirects and errors over plain HTTP
+//! on a free local port, so that downloads can be tested without network
+//! access by pointing [`super::DownloadOptions::base_url`] at it. Every
+//! connection is answered on its own thread and closed after one response.
+//!
+//! The module also holds the fixtures shared by the tests of the commands: a
+//! contents directory linking some references ([`write_contents`]) and
+//! download options for a local server ([`local_options`]).
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use super::{DownloadOptions, DownloadSummary, download_references};
+use crate::{cache::sha256_hex, references::CPPREFERENCE_URL_PREFIX};
+
+/// How the server answers a path
+#[derive(Debug, Clone)]
+pub enum Route {
+    /// A `200 OK` HTML page with an `ETag`, or `304 Not Modified` if the
+    /// request carries the same `ETag` in `If-None-Match`
+    Page(String),
+    /// A `301 Moved Permanently` redirect to a URL or path
+    Redirect(String),
+    /// An error status with a short plain text body
+    Status(u16),
+    /// Another route answered after a delay
+    Slow(Duration, Box<Route>),
+}
+
+/// A local HTTP server answering fixed routes
+///
+/// Paths without a route are answered with `404 Not Found`. The server stops
+/// when it is dropped.
+pub struct TestServer {
+    /// The address the server listens on
+    addr: SocketAddr,
+    /// The paths requested so far, in order of arrival
+    requests: Arc<Mutex<Vec<String>>>,
+    /// Set when the server is dropped
+    stopped: Arc<AtomicBool>,
+}
+
+impl TestServer {
+    /// Start a server on a free port of 127.0.0.1
+    ///
+    /// # Arguments
+    ///
+    /// * `routes` - The routes by path, including any query string (e.g. `/w/cpp/algorithm/sort`)
+    pub fn start(routes: impl IntoIterator<Item = (String, Route)>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let routes: Arc<HashMap<String, Route>> = Arc::new(routes.into_iter().collect());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let (server_requests, server_stopped) = (requests.clone(), stopped.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if server_stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let (routes, requests) = (routes.clone(), server_requests.clone());
+                thread::spawn(move || {
+                    // A client hanging up early is not an error of the server
+                    let _ = handle(stream, &routes, &requests);
+                });
+            }
+        });
+
+        TestServer {
+            addr,
+            requests,
+            stopped,
+        }
+    }
+
+    /// The origin of the server (e.g. `http://127.0.0.1:4321`)
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// The paths requested so far, in order of arrival
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wake up the accepting thread so that it sees the flag
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+/// Answer a single request
+fn handle(
+    mut stream: TcpStream,
+    routes: &HashMap<String, Route>,
+    requests: &Mutex<Vec<String>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut if_none_match = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("if-none-match")
+        {
+            if_none_match = Some(value.trim().to_string());
+        }
+    }
+
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_string();
+    requests.lock().unwrap().push(path.clone());
+
+    let mut route = routes.get(&path).cloned().unwrap_or(Route::Status(404));
+    while let Route::Slow(delay, inner) = route {
+        thread::sleep(delay);
+        route = *inner;
+    }
+    let (status, headers, body) = match route {
+        Route::Page(body) => {
+            let etag = format!("\"{}\"", sha256_hex(body.as_bytes()));
+            if if_none_match.as_ref() == Some(&etag) {
+                (304, format!("ETag: {}\r\n", etag), String::new())
+            } else {
+                (
+                    200,
+                    format!(
+                        "Content-Type: text/html; charset=utf-8\r\nETag: {}\r\n",
+                        etag
+                    ),
+                    body,
+                )
+            }
+        }
+        Route::Redirect(location) => (301, format!("Location: {}\r\n", location), String::new()),
+        Route::Status(status) => (status, String::new(), format!("status {}", status)),
+        Route::Slow(..) => unreachable!(),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        headers,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// The reason phrase of a status code
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        301 => "Moved Permanently",
+        304 => "Not Modified",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+/// A page laid out like a cppreference article
+///
+/// The page has the navigation elements of [`crate::sources::NAVIGATION_SELECTORS`]
+/// and enough content to pass [`super::validate::validate_page`].
+///
+/// # Arguments
+///
+/// * `lang` - The `<html lang>` attribute (e.g. `zh-Hans`)
+/// * `title` - The title of the page
+pub fn fixture_page(lang: &str, title: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html><html lang="{lang}"><head><title>{title}</title></head><body><div id="mw-head">navigation</div><h1 id="firstHeading">{title}</h1><div id="mw-content-text"><div class="t-navbar">navbar</div><p>{text}</p></div></body></html>"#,
+        lang = lang,
+        title = title,
+        text = format!("{} sorts the elements in the range [first, last). ", title).repeat(10)
+    )
+}
+
+/// Create a contents directory with a Markdown table linking references
+///
+/// # Arguments
+///
+/// * `dir` - The directory the `contents` directory is created in
+/// * `links` - The reference names with the path of their English page below
+///   `/w/` and without `.html` (e.g. `("std::sort", "cpp/algorithm/sort")`)
+///
+/// # Returns
+///
+/// The contents directory.
+pub fn write_contents(dir: &Path, links: &[(&str, &str)]) -> PathBuf {
+    let contents_dir = dir.join("contents");
+    fs::create_dir_all(&contents_dir).unwrap();
+    let mut table = "| F | C++ |\n| - | - |\n".to_string();
+    for (name, path) in links {
+        table.push_str(&format!(
+            "| 功能 | [`{}`]({}{}.html) |\n",
+            name, CPPREFERENCE_URL_PREFIX, path
+        ));
+    }
+    fs::write(contents_dir.join("Algorithms.md"), table).unwrap();
+    contents_dir
+}
+
+/// Options downloading the Chinese pages from a local server
+///
+/// Requests are neither rate limited, delayed nor retried.
+///
+/// # Arguments
+///
+/// * `contents_dir` - The contents directory, usually from [`write_contents`]
+/// * `output_dir` - The cache directory
+/// * `base_url` - The origin of the server
+pub fn local_options(contents_dir: &Path, output_dir: &Path, base_url: &str) -> DownloadOptions {
+    let mut options = DownloadOptions::new("zh");
+    options.contents_dir = contents_dir.to_path_buf();
+    options.output_dir = output_dir.to_path_buf();
+    options.requests_per_second = 0.0;
+    options.jitter = Duration::ZERO;
+    options.retries = 0;
+    options.base_url = Some(base_url.to_string());
+    options
+}
+
+/// Run a download to completion
+pub fn download(options: &DownloadOptions) -> DownloadSummary {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(download_references(options))
+        .unwrap()
+}
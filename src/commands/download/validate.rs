@@ -0,0 +1,149 @@
+//! Validating downloaded pages
+//!
+//! A successful response is not necessarily a reference page: a bot challenge
+//! (e.g. Cloudflare's "Just a moment..."), a login wall, an English page served
+//! by a translated site, or a page cut off in transfer would otherwise be
+//! stored and printed. [`validate_page`] checks that a page looks like a
+//! complete cppreference article in the language of its source.
+
+use scraper::{Html, Selector};
+
+use crate::{errors::AppError, sources::ReferenceSource};
+
+/// Minimum number of characters of text in the article body
+///
+/// The shortest reference pages (e.g. a single macro) have well over a
+/// thousand characters; error pages and truncated downloads have much less.
+pub const MIN_CONTENT_CHARS: usize = 200;
+
+/// Markers of bot challenges and login walls
+const CHALLENGE_MARKERS: [&str; 8] = [
+    "cf-browser-verification",
+    "cf_chl_opt",
+    "challenge-platform",
+    "<title>Just a moment...</title>",
+    "Attention Required! | Cloudflare",
+    "Checking your browser before accessing",
+    "g-recaptcha",
+    "wpLoginAttempt",
+];
+
+/// Check that a downloaded page is a complete article of its source
+///
+/// The page must:
+/// - not contain markers of bot challenges or login walls
+/// - declare the language of the source in `<html lang>` (e.g. `zh-hans` for `zh`)
+/// - have a `#firstHeading` title and a `#mw-content-text` body
+/// - have at least [`MIN_CONTENT_CHARS`] characters of text in its body
+///
+/// # Arguments
+///
+/// * `content` - The HTML content as downloaded
+/// * `url` - The URL of the page, for the error
+/// * `source` - The source the page was downloaded from
+///
+/// # Errors
+///
+/// Returns [`AppError::InvalidPage`] listing every failed check.
+pub fn validate_page(
+    content: &str,
+    url: &str,
+    source: &dyn ReferenceSource,
+) -> Result<(), AppError> {
+    let mut problems = Vec::new();
+
+    if let Some(marker) = CHALLENGE_MARKERS
+        .iter()
+        .find(|marker| content.contains(*marker))
+    {
+        problems.push(format!("challenge or login page ({})", marker));
+    }
+
+    let html = Html::parse_document(content);
+    let select = |selector: &str| {
+        let selector = Selector::parse(selector).unwrap();
+        html.select(&selector).next()
+    };
+
+    let lang = select("html").and_then(|element| element.value().attr("lang"));
+    let primary = lang.and_then(|lang| lang.split(['-', '_']).next());
+    if !primary.is_some_and(|primary| primary.eq_ignore_ascii_case(source.lang())) {
+        problems.push(format!(
+            "language {} instead of {}",
+            lang.unwrap_or("(none)"),
+            source.lang()
+        ));
+    }
+
+    if select("#firstHeading").is_none() {
+        problems.push("no #firstHeading".to_string());
+    }
+    match select("#mw-content-text") {
+        None => problems.push("no #mw-content-text".to_string()),
+        Some(body) => {
+            let chars: usize = body
+                .text()
+                .map(|text| text.chars().filter(|c| !c.is_whitespace()).count())
+                .sum();
+            if chars < MIN_CONTENT_CHARS {
+                problems.push(format!(
+                    "only {} characters of content (at least {} expected)",
+                    chars, MIN_CONTENT_CHARS
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError::InvalidPage {
+            url: url.to_string(),
+            reason: problems.join("; "),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sources::source_for;
+
+    /// A page with the given `lang` attribute and body text
+    fn page(lang: &str, text: &str) -> String {
+        format!(
+            r#"<!DOCTYPE html><html lang="{}"><head><title>std::sort</title></head><body><h1 id="firstHeading">std::sort</h1><div id="mw-content-text"><p>{}</p></div></body></html>"#,
+            lang, text
+        )
+    }
+
+    fn reason(result: Result<(), AppError>) -> String {
+        match result {
+            Err(AppError::InvalidPage { reason, .. }) => reason,
+            other => panic!("Expected InvalidPage, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_validate_page() {
+        let url = "https://cppreference.cn/w/cpp/algorithm/sort";
+        let zh = source_for("zh").unwrap();
+        let text = "按非降序排序范围 [first, last) 中的元素。".repeat(20);
+
+        assert!(validate_page(&page("zh-hans", &text), url, zh).is_ok());
+        assert_eq!(
+            reason(validate_page(&page("en", &text), url, zh)),
+            "language en instead of zh"
+        );
+        assert!(
+            reason(validate_page(&page("zh-hans", "短"), url, zh))
+                .starts_with("only 1 characters of content")
+        );
+
+        let challenge = r#"<!DOCTYPE html><html lang="en-US"><head><title>Just a moment...</title></head><body><div id="challenge-platform"></div></body></html>"#;
+        let reason = reason(validate_page(challenge, url, zh));
+        assert!(reason.starts_with("challenge or login page"), "{}", reason);
+        assert!(reason.contains("no #firstHeading"), "{}", reason);
+        assert!(reason.contains("no #mw-content-text"), "{}", reason);
+    }
+}
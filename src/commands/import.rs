@@ -0,0 +1,403 @@
+//! Import command implementation
+//!
+//! This module fills the cache from the offline HTML book that cppreference
+//! publishes, so that pages can be printed without network access. The
+//! archive can be given as the downloaded zip file or as the directory it was
+//! extracted to. Every reference in the Markdown files is looked up in the
+//! archive by the path of its English URL, processed like a downloaded page
+//! and stored in the same cache layout, so `ref print` works unchanged.
+
+use log::{debug, error, info, warn};
+use std::{
+    collections::HashMap,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use super::download::{DownloadStatus, DownloadSummary};
+use crate::{
+    cache::{PageCache, PageMetadata, sha256_hex},
+    config::Config,
+    errors::AppError,
+    html::{PROCESSING_VERSION, remove_navigation_elements},
+    references::{CPPREFERENCE_URL_PREFIX, get_required_references},
+};
+
+/// Selectors of the navigation elements of archive pages
+///
+/// The archive is preprocessed for offline reading: the wiki header
+/// (`#mw-head`) and other non-printable elements are already gone, but the
+/// navigation bar at the top of every page is kept.
+pub const ARCHIVE_NAVIGATION_SELECTORS: [&str; 1] = [".t-navbar"];
+
+/// Directories of the archive that English pages are stored under
+///
+/// The HTML book stores `/w/cpp/container/vector.html` as
+/// `reference/en/cpp/container/vector.html`; raw mirrors of the site keep the
+/// host name and the `/w/` prefix.
+const PAGE_ROOTS: [&str; 3] = ["reference/en/", "en.cppreference.com/w/", "en/"];
+
+/// Options for [`import_references`]
+#[derive(Debug, Clone)]
+pub struct ImportOptions {
+    /// Directory containing the Markdown files with C++ reference links
+    pub contents_dir: PathBuf,
+    /// The offline archive: a zip file or the directory it was extracted to
+    pub archive: PathBuf,
+    /// Directory the processed HTML pages are written to
+    pub output_dir: PathBuf,
+    /// Whether to overwrite pages that are already cached
+    pub overwrite: bool,
+}
+
+impl ImportOptions {
+    /// Create options using the default directory layout
+    ///
+    /// Markdown files are read from `./contents` and pages are written to
+    /// `./cppreference_en`, since the archive holds the English pages.
+    /// Existing files are not overwritten.
+    pub fn new(archive: &Path) -> Self {
+        Config::default().import_options(archive)
+    }
+}
+
+/// Import C++ reference pages from an offline archive
+///
+/// This function:
+/// 1. Gets all required C++ references from Markdown files in the contents directory
+/// 2. Indexes the HTML pages of the archive by the path of their English URL
+/// 3. Reads the page of every reference that is not cached yet (or of every
+///    reference if `overwrite` is set) and removes its navigation elements
+/// 4. Writes the pages to the output directory and records them in its manifest
+///
+/// # Arguments
+///
+/// * `options` - Directories, archive and overwrite behavior
+///
+/// # Returns
+///
+/// The outcome of every reference. References missing from the archive, or
+/// whose page cannot be read or processed, are reported as failed without
+/// stopping the others; use [`DownloadSummary::check`] to turn them into an
+/// error.
+///
+/// # Errors
+///
+/// Returns an error if:
+/// - Reference extraction fails
+/// - The archive cannot be read or is not a valid zip file
+/// - The output directory or a page cannot be written
+pub fn import_references(options: &ImportOptions) -> Result<DownloadSummary, AppError> {
+    info!("Importing C++ references from {:?}", options.archive);
+
+    let references = get_required_references(&options.contents_dir)?;
+    let mut archive = Archive::open(&options.archive)?;
+    let pages = index_pages(archive.html_files()?);
+    if pages.is_empty() {
+        warn!(
+            "No cppreference pages found in {:?}; expected paths such as {}cpp/container/vector.html",
+            options.archive, PAGE_ROOTS[0]
+        );
+    }
+    info!(
+        "Found {} page(s) in the archive for {} reference(s)",
+        pages.len(),
+        references.len()
+    );
+
+    let output_dir = options.output_dir.as_path();
+    if !output_dir.exists() {
+        info!("Creating output directory: {:?}", output_dir);
+        fs::create_dir_all(output_dir)?;
+    }
+    let mut cache = PageCache::open(output_dir)?;
+    let mut summary = DownloadSummary::default();
+
+    let mut references: Vec<_> = references.into_iter().collect();
+    references.sort_by(|a, b| a.0.cmp(&b.0));
+    for (name, reference) in references {
+        if cache.contains(&name) && !options.overwrite {
+            debug!("{} is already cached, skipping import", name);
+            summary.push(&name, DownloadStatus::Skipped, None);
+            continue;
+        }
+
+        let Some(path) = page_key(&reference.url).and_then(|key| pages.get(&key)) else {
+            error!("{} ({}) is not in the archive", name, reference.url);
+            summary.push(
+                &name,
+                DownloadStatus::Failed,
+                Some(format!("{} is not in the archive", reference.url)),
+            );
+            continue;
+        };
+
+        let page = archive.read(path).and_then(|raw| {
+            let content = remove_navigation_elements(&raw, &name, &ARCHIVE_NAVIGATION_SELECTORS)?;
+            Ok((raw, content))
+        });
+        let (raw, content) = match page {
+            Ok(page) => page,
+            Err(e) => {
+                error!("Failed to import {} from {}: {}", name, path, e);
+                summary.push(&name, DownloadStatus::Failed, Some(e.to_string()));
+                continue;
+            }
+        };
+        let raw_sha256 = cache.write_raw(&name, &raw)?;
+        let output_path = cache.page_path(&name);
+        let sha256 = sha256_hex(content.as_bytes());
+        let unchanged = output_path.is_file()
+            && cache.entry(&name).is_some_and(|entry| {
+                entry.metadata.sha256.as_ref() == Some(&sha256) && entry.metadata.is_current(false)
+            });
+        if !unchanged {
+            fs::write(&output_path, &content)?;
+            debug!("Imported {} from {} to {:?}", name, path, output_path);
+        }
+        cache.insert(
+            &name,
+            PageMetadata {
+                url: Some(reference.url),
+                sha256: Some(sha256),
+                processing_version: Some(PROCESSING_VERSION),
+                raw_sha256: Some(raw_sha256),
+                archive: Some(path.clone()),
+                ..PageMetadata::default()
+            },
+        );
+        summary.push(
+            &name,
+            if unchanged {
+                DownloadStatus::Unchanged
+            } else {
+                DownloadStatus::Succeeded
+            },
+            None,
+        );
+    }
+    cache.save()?;
+
+    info!("Import completed: {}", summary.totals());
+    summary.sort();
+    Ok(summary)
+}
+
+/// An offline archive, either extracted or as a zip file
+enum Archive {
+    /// The directory the archive was extracted to
+    Dir(PathBuf),
+    /// The zip file
+    Zip(zip::ZipArchive<fs::File>),
+}
+
+impl Archive {
+    /// Open a directory or zip file
+    fn open(path: &Path) -> Result<Archive, AppError> {
+        if path.is_dir() {
+            Ok(Archive::Dir(path.to_path_buf()))
+        } else {
+            Ok(Archive::Zip(zip::ZipArchive::new(fs::File::open(path)?)?))
+        }
+    }
+
+    /// The paths of all HTML files, relative to the archive root and separated by `/`
+    fn html_files(&self) -> Result<Vec<String>, AppError> {
+        let mut files = Vec::new();
+        match self {
+            Archive::Dir(root) => collect_files(root, "", &mut files)?,
+            Archive::Zip(zip) => files.extend(zip.file_names().map(str::to_string)),
+        }
+        files.retain(|file| file.ends_with(".html"));
+        Ok(files)
+    }
+
+    /// Read a file of the archive
+    fn read(&mut self, path: &str) -> Result<String, AppError> {
+        match self {
+            Archive::Dir(root) => Ok(fs::read_to_string(root.join(path))?),
+            Archive::Zip(zip) => {
+                let mut content = String::new();
+                zip.by_name(path)?.read_to_string(&mut content)?;
+                Ok(content)
+            }
+        }
+    }
+}
+
+/// Recursively collect the paths of the files in a directory
+///
+/// # Arguments
+///
+/// * `dir` - The directory to search
+/// * `prefix` - The path of `dir` relative to the archive root, ending in `/` unless empty
+/// * `files` - The list the paths are added to
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<String>) -> Result<(), AppError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, &format!("{}{}/", prefix, name), files)?;
+        } else {
+            files.push(format!("{}{}", prefix, name));
+        }
+    }
+    Ok(())
+}
+
+/// Index HTML files by the page they hold
+///
+/// # Returns
+///
+/// A map from page keys (see [`page_key`]) to paths in the archive. If
+/// several files hold the same page, the shortest path wins.
+fn index_pages(mut files: Vec<String>) -> HashMap<String, String> {
+    files.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    let mut pages = HashMap::new();
+    for file in files {
+        let relative = format!("/{}", file);
+        let page = PAGE_ROOTS.iter().find_map(|root| {
+            relative
+                .find(&format!("/{}", root))
+                .map(|index| &relative[index + root.len() + 1..])
+        });
+        if let Some(page) = page {
+            let key = page.trim_end_matches(".html").to_string();
+            pages.entry(key).or_insert(file);
+        }
+    }
+    pages
+}
+
+/// The key of the page an English URL points to
+///
+/// This is the path below `/w/` without the `.html` suffix and fragment,
+/// e.g. `cpp/container/vector` for
+/// `https://en.cppreference.com/w/cpp/container/vector.html#Iterators`.
+fn page_key(url: &str) -> Option<String> {
+    let path = url.strip_prefix(CPPREFERENCE_URL_PREFIX)?;
+    let path = path.split_once('#').map_or(path, |(path, _)| path);
+    Some(
+        path.trim_end_matches('/')
+            .trim_end_matches(".html")
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::download::test_server::write_contents;
+    use std::io::Write;
+
+    const PAGE: &str = r#"<!DOCTYPE html><html><body><div class="t-navbar">nav</div><h1>std::vector</h1></body></html>"#;
+
+    /// The references linked by the contents of the tests
+    const LINKS: [(&str, &str); 2] = [
+        ("std::vector", "cpp/container/vector"),
+        ("std::missing", "cpp/missing"),
+    ];
+
+    #[test]
+    fn test_import_from_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("html_book");
+        let page_dir = archive.join("reference/en/cpp/container");
+        fs::create_dir_all(&page_dir).unwrap();
+        fs::write(page_dir.join("vector.html"), PAGE).unwrap();
+
+        let mut options = ImportOptions::new(&archive);
+        options.contents_dir = write_contents(temp_dir.path(), &LINKS);
+        options.output_dir = temp_dir.path().join("cppreference_en");
+
+        let summary = import_references(&options).unwrap();
+        assert_eq!(summary.count(DownloadStatus::Succeeded), 1);
+        assert_eq!(summary.count(DownloadStatus::Failed), 1);
+        assert!(matches!(
+            summary.check(),
+            Err(AppError::DownloadFailed {
+                failed: 1,
+                total: 2
+            })
+        ));
+
+        let cache = PageCache::open(&options.output_dir).unwrap();
+        let content = fs::read_to_string(cache.page_path("std::vector")).unwrap();
+        assert!(content.contains("<h1>std::vector</h1>"));
+        assert!(!content.contains("t-navbar"));
+        let entry = cache.entry("std::vector").unwrap();
+        assert_eq!(
+            entry.metadata.url.as_deref(),
+            Some("https://en.cppreference.com/w/cpp/container/vector.html")
+        );
+
+        let summary = import_references(&options).unwrap();
+        assert_eq!(summary.count(DownloadStatus::Skipped), 1);
+
+        // An unreadable page fails without stopping the import
+        fs::write(archive.join("reference/en/cpp/missing.html"), [0xff, 0xfe]).unwrap();
+        options.overwrite = true;
+        let summary = import_references(&options).unwrap();
+        let outcome: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            outcome,
+            [
+                ("std::vector", DownloadStatus::Unchanged),
+                ("std::missing", DownloadStatus::Failed),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_import_from_zip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let archive = temp_dir.path().join("html_book.zip");
+        let mut zip = zip::ZipWriter::new(fs::File::create(&archive).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("html_book/reference/en/cpp/container/vector.html", options)
+            .unwrap();
+        zip.write_all(PAGE.as_bytes()).unwrap();
+        zip.start_file("html_book/reference/common/site_modules.css", options)
+            .unwrap();
+        zip.finish().unwrap();
+
+        let mut options = ImportOptions::new(&archive);
+        options.contents_dir = write_contents(temp_dir.path(), &LINKS);
+        options.output_dir = temp_dir.path().join("cppreference_en");
+
+        let summary = import_references(&options).unwrap();
+        assert_eq!(summary.count(DownloadStatus::Succeeded), 1);
+        let cache = PageCache::open(&options.output_dir).unwrap();
+        assert!(cache.contains("std::vector"));
+        assert!(!cache.contains("std::missing"));
+    }
+
+    #[test]
+    fn test_page_keys() {
+        assert_eq!(
+            page_key("https://en.cppreference.com/w/cpp/container/vector.html#Iterators")
+                .as_deref(),
+            Some("cpp/container/vector")
+        );
+        assert_eq!(page_key("https://example.com/"), None);
+
+        let pages = index_pages(vec![
+            "en.cppreference.com/w/cpp/algorithm/sort.html".to_string(),
+            "book/reference/en/cpp/algorithm/sort.html".to_string(),
+            "reference/en/c/io/fprintf.html".to_string(),
+            "reference/common/index.html".to_string(),
+        ]);
+        assert_eq!(
+            pages.get("cpp/algorithm/sort").map(String::as_str),
+            Some("book/reference/en/cpp/algorithm/sort.html")
+        );
+        assert!(pages.contains_key("c/io/fprintf"));
+        assert_eq!(pages.len(), 2);
+    }
+}
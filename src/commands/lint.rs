@@ -0,0 +1,649 @@
+//! Lint command implementation
+//!
+//! This module checks the Markdown files in the contents directory for
+//! mistakes that the extractor would otherwise skip silently or only report
+//! one at a time: malformed table rows, links that will not be downloaded,
+//! names without links and inconsistent table headers. The report can be
+//! printed as text or as JSON, and fails if there are errors (or warnings,
+//! in strict mode), so it can be used in a pre-commit hook.
+
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    commands::OutputFormat,
+    config::Config,
+    errors::AppError,
+    references::{
+        CPP_COLUMN_HEADER, CPPREFERENCE_URL_PREFIX, deduplicate_references, extract_references,
+        kind::{LOWERCASE_MACROS, ReferenceKind},
+        markdown::{Inline, Table, parse_markdown},
+    },
+    utils::find_markdown_files,
+};
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Always fails the lint
+    Error,
+    /// Fails the lint in strict mode
+    Warning,
+}
+
+/// The check that produced a diagnostic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintRule {
+    /// A row has a different number of cells than its header
+    ColumnCount,
+    /// A link in a `C++` column has no URL
+    MissingUrl,
+    /// A link in a `C++` column has no backticked name
+    MissingName,
+    /// A link in a `C++` column does not point at a cppreference.com C or C++ page
+    ForeignHost,
+    /// A name is linked to different pages
+    ConflictingUrl,
+    /// Some URLs end in `.html` and others do not
+    MixedUrlSuffix,
+    /// A linked name without namespace is neither upper case nor a known macro
+    UnqualifiedName,
+    /// A backticked name in a `C++` column is not linked
+    UnlinkedName,
+    /// A `C++` cell is empty
+    EmptyCell,
+    /// A header is spelled or ordered differently than in other tables
+    HeaderMismatch,
+}
+
+impl LintRule {
+    /// The severity of diagnostics produced by the rule
+    pub fn severity(self) -> Severity {
+        match self {
+            LintRule::ColumnCount
+            | LintRule::MissingUrl
+            | LintRule::MissingName
+            | LintRule::ForeignHost
+            | LintRule::ConflictingUrl => Severity::Error,
+            LintRule::MixedUrlSuffix
+            | LintRule::UnqualifiedName
+            | LintRule::UnlinkedName
+            | LintRule::EmptyCell
+            | LintRule::HeaderMismatch => Severity::Warning,
+        }
+    }
+
+    /// The name of the rule as used in reports (e.g. `column-count`)
+    pub fn name(self) -> &'static str {
+        match self {
+            LintRule::ColumnCount => "column-count",
+            LintRule::MissingUrl => "missing-url",
+            LintRule::MissingName => "missing-name",
+            LintRule::ForeignHost => "foreign-host",
+            LintRule::ConflictingUrl => "conflicting-url",
+            LintRule::MixedUrlSuffix => "mixed-url-suffix",
+            LintRule::UnqualifiedName => "unqualified-name",
+            LintRule::UnlinkedName => "unlinked-name",
+            LintRule::EmptyCell => "empty-cell",
+            LintRule::HeaderMismatch => "header-mismatch",
+        }
+    }
+}
+
+/// A problem found in a Markdown file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// The check that found the problem
+    pub rule: LintRule,
+    /// How serious the problem is
+    pub severity: Severity,
+    /// The Markdown file
+    pub file: PathBuf,
+    /// Line of the problem (1-based)
+    pub line: usize,
+    /// Column of the problem (1-based, in characters)
+    pub column: usize,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(
+            f,
+            "{}:{}:{}: {}[{}]: {}",
+            self.file.display(),
+            self.line,
+            self.column,
+            severity,
+            self.rule.name(),
+            self.message
+        )
+    }
+}
+
+/// The result of [`lint_references`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LintReport {
+    /// Number of errors
+    pub errors: usize,
+    /// Number of warnings
+    pub warnings: usize,
+    /// All diagnostics, ordered by file and position
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    /// Format the report
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if JSON serialization fails.
+    pub fn render(&self, format: OutputFormat) -> Result<String, AppError> {
+        match format {
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            OutputFormat::Text => {
+                let mut output = String::new();
+                for diagnostic in &self.diagnostics {
+                    output.push_str(&format!("{}\n", diagnostic));
+                }
+                output.push_str(&format!(
+                    "{} error(s), {} warning(s)\n",
+                    self.errors, self.warnings
+                ));
+                Ok(output)
+            }
+        }
+    }
+
+    /// Check whether the lint passed
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::LintFailed`] if there are errors, or warnings and
+    /// `strict` is set.
+    pub fn check(&self, strict: bool) -> Result<(), AppError> {
+        if self.errors > 0 || (strict && self.warnings > 0) {
+            return Err(AppError::LintFailed {
+                errors: self.errors,
+                warnings: self.warnings,
+            });
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, rule: LintRule, file: &Path, line: usize, column: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            rule,
+            severity: rule.severity(),
+            file: file.to_path_buf(),
+            line,
+            column,
+            message,
+        });
+    }
+}
+
+/// Options for [`lint_references`]
+#[derive(Debug, Clone)]
+pub struct LintOptions {
+    /// Directory containing the Markdown files with C++ reference links
+    pub contents_dir: PathBuf,
+    /// Output format
+    pub format: OutputFormat,
+    /// Whether warnings fail the lint
+    pub strict: bool,
+}
+
+impl LintOptions {
+    /// Create options using the default directory layout
+    pub fn new(format: OutputFormat) -> Self {
+        LintOptions {
+            contents_dir: Config::default().contents_dir(),
+            format,
+            strict: false,
+        }
+    }
+}
+
+/// A cppreference.com link in a `C++` column, for the suffix check
+struct SuffixUse {
+    file: PathBuf,
+    line: usize,
+    column: usize,
+    html: bool,
+}
+
+/// Header spellings and column orders seen so far, for the header check
+#[derive(Default)]
+struct HeaderIndex {
+    /// The first spelling of every normalized header, with where it was seen
+    spellings: HashMap<String, (String, PathBuf, usize)>,
+    /// Pairs of columns in the order they were first seen in
+    orders: HashMap<(String, String), (PathBuf, usize)>,
+}
+
+/// Lint the Markdown files in the contents directory
+///
+/// This function checks every table with a `C++` column for:
+///
+/// - Rows with a different number of cells than the header (error)
+/// - Links without a URL or without a backticked name (error)
+/// - Links that are not cppreference.com C++ pages and are skipped (error)
+/// - Names or aliases linked to different pages (error)
+/// - A mix of URLs with and without the `.html` suffix (warning)
+/// - Names outside the `std` namespace (warning)
+/// - Backticked names without a link, and empty `C++` cells (warning)
+///
+/// Headers of all tables are also compared across files, and spellings that
+/// differ only in case or plural (`Container` and `Containers`) or columns
+/// in a different order are reported as warnings.
+///
+/// # Arguments
+///
+/// * `options` - Contents directory, output format and strictness
+///
+/// # Returns
+///
+/// The report. Use [`LintReport::check`] to turn failures into an error.
+///
+/// # Errors
+///
+/// Returns an error if the Markdown files cannot be read.
+pub fn lint_references(options: &LintOptions) -> Result<LintReport, AppError> {
+    let files = find_markdown_files(&options.contents_dir)?;
+    let mut report = LintReport::default();
+    let mut headers = HeaderIndex::default();
+    let mut suffixes = Vec::new();
+    let mut references = Vec::new();
+
+    for file in &files {
+        let content = fs::read_to_string(file)?;
+        lint_file(file, &content, &mut report, &mut headers, &mut suffixes);
+
+        // Files the extractor rejects have already been reported above
+        if let Ok(extracted) = extract_references(std::slice::from_ref(file)) {
+            references.extend(extracted);
+        }
+    }
+
+    if let Err(AppError::DuplicateConflicts { conflicts, .. }) = deduplicate_references(references)
+    {
+        for conflict in conflicts {
+            let Some(source) = &conflict.source2 else {
+                continue;
+            };
+            report.push(
+                LintRule::ConflictingUrl,
+                &source.file,
+                source.line,
+                source.column,
+                conflict.to_string(),
+            );
+        }
+    }
+
+    lint_suffixes(&suffixes, &mut report);
+
+    report.diagnostics.sort_by(|a, b| {
+        (&a.file, a.line, a.column, a.rule.name()).cmp(&(&b.file, b.line, b.column, b.rule.name()))
+    });
+    report.errors = report
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    report.warnings = report.diagnostics.len() - report.errors;
+    Ok(report)
+}
+
+/// Lint the tables of one Markdown file
+fn lint_file(
+    file: &Path,
+    content: &str,
+    report: &mut LintReport,
+    headers: &mut HeaderIndex,
+    suffixes: &mut Vec<SuffixUse>,
+) {
+    let lines: Vec<&str> = content.lines().collect();
+    let document = parse_markdown(content);
+
+    for table in &document.tables {
+        lint_header(file, table, report, headers);
+
+        let Some(cpp_column) = table
+            .header
+            .iter()
+            .position(|cell| cell.text() == CPP_COLUMN_HEADER)
+        else {
+            continue;
+        };
+
+        // The parser pads and truncates rows, so cells are counted in the source
+        let header_cells = count_raw_cells(lines.get(table.line - 1).unwrap_or(&""));
+        for row in &table.rows {
+            let cells = count_raw_cells(lines.get(row.line - 1).unwrap_or(&""));
+            if cells != header_cells {
+                report.push(
+                    LintRule::ColumnCount,
+                    file,
+                    row.line,
+                    1,
+                    format!("Row has {} cells, the header has {}", cells, header_cells),
+                );
+            }
+
+            let Some(cell) = row.cells.get(cpp_column) else {
+                continue;
+            };
+            for inline in &cell.content {
+                if let Inline::Code(name) = inline {
+                    report.push(
+                        LintRule::UnlinkedName,
+                        file,
+                        cell.line,
+                        cell.column,
+                        format!("`{}` is not linked to a page", name),
+                    );
+                }
+            }
+            if cell.links.is_empty() && cell.text().is_empty() {
+                report.push(
+                    LintRule::EmptyCell,
+                    file,
+                    cell.line,
+                    cell.column,
+                    "The C++ cell is empty".to_string(),
+                );
+            }
+
+            for link in &cell.links {
+                if link.url.is_empty() {
+                    report.push(
+                        LintRule::MissingUrl,
+                        file,
+                        link.line,
+                        link.column,
+                        "Link has no URL".to_string(),
+                    );
+                    continue;
+                }
+                let Some(link_kind) = ReferenceKind::classify(&link.url, "") else {
+                    report.push(
+                        LintRule::ForeignHost,
+                        file,
+                        link.line,
+                        link.column,
+                        format!(
+                            "{} will not be downloaded, only pages below {}cpp/ and {}c/ are",
+                            link.url, CPPREFERENCE_URL_PREFIX, CPPREFERENCE_URL_PREFIX
+                        ),
+                    );
+                    continue;
+                };
+
+                let path = link
+                    .url
+                    .split_once('#')
+                    .map_or(link.url.as_str(), |(url, _)| url);
+                suffixes.push(SuffixUse {
+                    file: file.to_path_buf(),
+                    line: link.line,
+                    column: link.column,
+                    html: path.ends_with(".html"),
+                });
+
+                let mut names = link.code_spans().peekable();
+                let named_by_text = link_kind.allows_plain_names() && !link.text().is_empty();
+                if names.peek().is_none() && !named_by_text {
+                    report.push(
+                        LintRule::MissingName,
+                        file,
+                        link.line,
+                        link.column,
+                        "Link has no backticked name".to_string(),
+                    );
+                }
+                for name in names.map(str::trim) {
+                    let is_macro =
+                        ReferenceKind::classify(&link.url, name) == Some(ReferenceKind::Macro);
+                    let is_upper_case = !name.chars().any(|c| c.is_ascii_lowercase());
+                    if is_macro && !is_upper_case && !LOWERCASE_MACROS.contains(&name) {
+                        report.push(
+                            LintRule::UnqualifiedName,
+                            file,
+                            link.line,
+                            link.column,
+                            format!(
+                                "`{}` has no namespace but is not a known macro, \
+                                 did you mean `std::{}`?",
+                                name, name
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Compare the header of a table with the headers seen so far
+fn lint_header(file: &Path, table: &Table, report: &mut LintReport, headers: &mut HeaderIndex) {
+    let labels: Vec<String> = table.header.iter().map(|cell| cell.text()).collect();
+
+    for label in &labels {
+        let key = normalize_header(label);
+        match headers.spellings.get(&key) {
+            Some((spelling, first_file, first_line)) if spelling != label => report.push(
+                LintRule::HeaderMismatch,
+                file,
+                table.line,
+                1,
+                format!(
+                    "Header `{}` is spelled `{}` in {}:{}",
+                    label,
+                    spelling,
+                    first_file.display(),
+                    first_line
+                ),
+            ),
+            Some(_) => {}
+            None => {
+                headers
+                    .spellings
+                    .insert(key, (label.clone(), file.to_path_buf(), table.line));
+            }
+        }
+    }
+
+    // The first column describes the row, the others are languages
+    let columns: Vec<String> = labels
+        .iter()
+        .skip(1)
+        .map(|label| normalize_header(label))
+        .collect();
+    for (i, a) in columns.iter().enumerate() {
+        for b in &columns[i + 1..] {
+            if let Some((first_file, first_line)) = headers.orders.get(&(b.clone(), a.clone())) {
+                report.push(
+                    LintRule::HeaderMismatch,
+                    file,
+                    table.line,
+                    1,
+                    format!(
+                        "Columns `{}` and `{}` are in the opposite order in {}:{}",
+                        a,
+                        b,
+                        first_file.display(),
+                        first_line
+                    ),
+                );
+            } else {
+                headers
+                    .orders
+                    .entry((a.clone(), b.clone()))
+                    .or_insert_with(|| (file.to_path_buf(), table.line));
+            }
+        }
+    }
+}
+
+/// Report the URLs using the less common suffix style
+fn lint_suffixes(suffixes: &[SuffixUse], report: &mut LintReport) {
+    let with_html = suffixes.iter().filter(|suffix| suffix.html).count();
+    let without_html = suffixes.len() - with_html;
+    if with_html == 0 || without_html == 0 {
+        return;
+    }
+
+    // Report the minority, or the URLs without suffix on a tie
+    let flag_html = with_html < without_html;
+    for suffix in suffixes.iter().filter(|suffix| suffix.html == flag_html) {
+        let message = if flag_html {
+            format!("URL ends in `.html`, unlike {} other link(s)", without_html)
+        } else {
+            format!(
+                "URL does not end in `.html`, unlike {} other link(s)",
+                with_html
+            )
+        };
+        report.push(
+            LintRule::MixedUrlSuffix,
+            &suffix.file,
+            suffix.line,
+            suffix.column,
+            message,
+        );
+    }
+}
+
+/// Normalize a header for comparison: case-insensitive and ignoring plurals
+fn normalize_header(label: &str) -> String {
+    let label = label.trim().to_lowercase();
+    if let Some(stem) = label.strip_suffix("ies") {
+        return format!("{}y", stem);
+    }
+    match label.strip_suffix('s') {
+        Some(singular) if !singular.is_empty() => singular.to_string(),
+        _ => label,
+    }
+}
+
+/// Count the cells of a table row in the Markdown source
+///
+/// Cells are separated by unescaped pipes. Leading and trailing pipes are
+/// optional, as in GFM.
+fn count_raw_cells(line: &str) -> usize {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let line = match line.strip_suffix('|') {
+        Some(rest) if !rest.ends_with('\\') => rest,
+        _ => line,
+    };
+
+    let mut cells = 1;
+    let mut escaped = false;
+    for c in line.chars() {
+        match c {
+            '\\' if !escaped => {
+                escaped = true;
+                continue;
+            }
+            '|' if !escaped => cells += 1,
+            _ => {}
+        }
+        escaped = false;
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_raw_cells() {
+        assert_eq!(count_raw_cells("| a | b | c |"), 3);
+        assert_eq!(count_raw_cells("a | b"), 2);
+        assert_eq!(count_raw_cells("| 计算绝对值 | | `Math.abs` |"), 3);
+        assert_eq!(count_raw_cells(r"| `a \| b` | c |"), 2);
+    }
+
+    #[test]
+    fn test_lint_references() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("Geometry.md"),
+            r#"# Geometry
+
+| Functionality | C++ | Java |
+| ------------- | --- | ---- |
+| 计算绝对值 | | `Math.abs` |
+| 计算中点 | [`std::midpoint`](https://en.cppreference.com/w/cpp/numeric/midpoint.html) |
+| 并查集 | `disjoint_set` | |
+| 排序 | [`sort`](https://en.cppreference.com/w/cpp/algorithm/sort) | |
+| 查找 | [`std::find`](https://example.com/find) | |
+"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("Sorting.md"),
+            r#"| Functionalities | Java | C++ |
+| --------------- | ---- | --- |
+| 排序 | | [`std::sort`](https://en.cppreference.com/w/cpp/algorithm/sort.html) |
+"#,
+        )
+        .unwrap();
+
+        let options = LintOptions {
+            contents_dir: temp_dir.path().to_path_buf(),
+            format: OutputFormat::Json,
+            strict: false,
+        };
+        let report = lint_references(&options).unwrap();
+        let found: Vec<_> = report
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.file.file_name().unwrap().to_str().unwrap(),
+                    diagnostic.line,
+                    diagnostic.rule,
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Geometry.md", 5, LintRule::EmptyCell),
+                ("Geometry.md", 6, LintRule::ColumnCount),
+                ("Geometry.md", 7, LintRule::UnlinkedName),
+                ("Geometry.md", 8, LintRule::MixedUrlSuffix),
+                ("Geometry.md", 8, LintRule::UnqualifiedName),
+                ("Geometry.md", 9, LintRule::ForeignHost),
+                ("Sorting.md", 1, LintRule::HeaderMismatch),
+                ("Sorting.md", 1, LintRule::HeaderMismatch),
+            ]
+        );
+        assert_eq!((report.errors, report.warnings), (2, 6));
+        assert!(matches!(
+            report.check(false),
+            Err(AppError::LintFailed { errors: 2, .. })
+        ));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&report.render(OutputFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["diagnostics"][1]["rule"], "column-count");
+        assert_eq!(json["diagnostics"][1]["severity"], "error");
+    }
+}
@@ -0,0 +1,133 @@
+//! List command implementation
+//!
+//! This module provides functionality to list the C++ references extracted
+//! from the Markdown files, together with where each one was found. The list
+//! can be printed as human-readable text or as JSON for other tooling.
+
+use std::path::PathBuf;
+
+use crate::{
+    commands::OutputFormat,
+    config::Config,
+    errors::AppError,
+    references::{CppReference, compare_references, get_required_references},
+};
+
+/// Options for [`list_references`]
+#[derive(Debug, Clone)]
+pub struct ListOptions {
+    /// Directory containing the Markdown files with C++ reference links
+    pub contents_dir: PathBuf,
+    /// Output format
+    pub format: OutputFormat,
+}
+
+impl ListOptions {
+    /// Create options using the default directory layout
+    pub fn new(format: OutputFormat) -> Self {
+        ListOptions {
+            contents_dir: Config::default().contents_dir(),
+            format,
+        }
+    }
+}
+
+/// List all required C++ references
+///
+/// This function extracts the references from the Markdown files, sorts them
+/// by kind and name and formats them with their aliases, standards and sources.
+///
+/// # Arguments
+///
+/// * `options` - Contents directory and output format
+///
+/// # Returns
+///
+/// The formatted list.
+///
+/// # Errors
+///
+/// Returns an error if reference extraction or JSON serialization fails.
+pub fn list_references(options: &ListOptions) -> Result<String, AppError> {
+    let unique_references = get_required_references(&options.contents_dir)?;
+
+    let mut references: Vec<&CppReference> = unique_references.values().collect();
+    references.sort_by(|a, b| compare_references(a, b));
+
+    match options.format {
+        OutputFormat::Json => Ok(serde_json::to_string_pretty(&references)?),
+        OutputFormat::Text => Ok(format_text(&references)),
+    }
+}
+
+/// Format references as human-readable text
+fn format_text(references: &[&CppReference]) -> String {
+    let mut output = String::new();
+
+    for reference in references {
+        let names: Vec<String> = reference
+            .names_with_standards()
+            .map(|(name, since)| match since {
+                Some(since) => format!("{} ({})", name, since),
+                None => name.to_string(),
+            })
+            .collect();
+        match reference.kind.label() {
+            Some(label) => output.push_str(&format!("{} ({})\n", names.join(", "), label)),
+            None => output.push_str(&format!("{}\n", names.join(", "))),
+        }
+        output.push_str(&format!("    {}\n", reference.url));
+        for source in &reference.sources {
+            output.push_str(&format!("    {} (line {})\n", source, source.line));
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_list_references_json() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(
+            temp_dir.path().join("Iteration.md"),
+            r#"# Iteration
+
+## Standard library
+### Functions
+| Functionality | C++ | Python |
+| ------------- | --- | ------ |
+| 去重 | [`std::ranges::unique` (C++20)](https://en.cppreference.com/w/cpp/algorithm/ranges/unique.html) | |
+"#,
+        )
+        .unwrap();
+
+        let options = ListOptions {
+            contents_dir: temp_dir.path().to_path_buf(),
+            format: OutputFormat::Json,
+        };
+        let output = list_references(&options).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+
+        let reference = &json[0];
+        assert_eq!(reference["name"], "std::ranges::unique");
+        assert_eq!(reference["since"], "C++20");
+        let source = &reference["sources"][0];
+        assert_eq!(source["title"], "Iteration");
+        assert_eq!(source["headings"][0], "Standard library");
+        assert_eq!(source["headings"][1], "Functions");
+        assert_eq!(source["row_label"], "去重");
+        assert_eq!(source["line"], 7);
+
+        let options = ListOptions {
+            format: OutputFormat::Text,
+            ..options
+        };
+        let output = list_references(&options).unwrap();
+        assert!(output.contains("Iteration.md › Standard library › Functions › 去重 (line 7)"));
+    }
+}
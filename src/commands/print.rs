@@ -1,39 +1,158 @@
 //! Print command implementation
 //!
 //! This module provides functionality to concatenate HTML files from the
-//! `./cppreference` directory into a single printable HTML file. It supports
+//! `./cppreference_{lang}` directory into a single printable HTML file. It supports
 //! two output modes:
 //!
 //! - **Colored**: Preserves syntax highlighting from the original pages
 //! - **Flattened**: Removes syntax highlighting for non-colored printing
+//!
+//! Pages are printed in name order by default. They can also be grouped by the
+//! Markdown files and sections they are documented in (see [`PrintOrder`]).
+//! Stylesheets and images downloaded with the pages are either linked or
+//! embedded (see [`AssetMode`]), so the printout renders without network access.
 
-use log::{error, info};
+use log::{error, info, warn};
 use markup5ever::{
     Attribute, LocalName, QualName,
     interface::{NodeOrText, TreeSink},
     tendril::StrTendril,
 };
 use scraper::{Html, HtmlTreeSink, Selector};
-use std::{collections::HashSet, fs, path::Path};
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::{
+    cache::{ASSETS_DIR_NAME, PageCache},
+    config::Config,
     errors::AppError,
-    html::flatten_code_blocks,
-    references::{compare_cpp_names, get_required_references},
+    html::{data_url, flatten_code_blocks, rewrite_asset_urls, rewrite_css_urls},
+    references::{
+        CppReference, compare_cpp_names, compare_references, get_required_references,
+        restrict_to_standard,
+        standard::{CppStandard, UnavailablePolicy},
+    },
+    sources::source_for,
+    utils::relative_url,
 };
 
+/// Order of the pages in the printout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrintOrder {
+    /// Sorted by name
+    #[default]
+    Name,
+    /// Grouped by Markdown file and section, each file starting on a new page
+    Topic,
+    /// In the order the references first appear in the Markdown files
+    Source,
+}
+
+impl FromStr for PrintOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(PrintOrder::Name),
+            "topic" => Ok(PrintOrder::Topic),
+            "source" => Ok(PrintOrder::Source),
+            _ => Err(format!(
+                "invalid order '{}', expected 'name', 'topic' or 'source'",
+                s
+            )),
+        }
+    }
+}
+
+/// How the printout uses the assets downloaded with the pages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetMode {
+    /// Link to the files in the cache directory
+    #[default]
+    Link,
+    /// Embed them as `data:` URLs, so that the printout is a single file
+    Inline,
+}
+
+impl FromStr for AssetMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "link" => Ok(AssetMode::Link),
+            "inline" => Ok(AssetMode::Inline),
+            _ => Err(format!(
+                "invalid asset mode '{}', expected 'link' or 'inline'",
+                s
+            )),
+        }
+    }
+}
+
+/// Options for [`print_references`]
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    /// Directory containing the Markdown files with C++ reference links
+    pub contents_dir: PathBuf,
+    /// Directory containing the downloaded HTML pages
+    pub input_dir: PathBuf,
+    /// File the concatenated HTML is written to
+    pub output_file: PathBuf,
+    /// Language version, selecting the source (see [`crate::sources`])
+    pub lang: String,
+    /// Whether to include colored output (preserve syntax highlighting)
+    pub colored: bool,
+    /// The C++ standard supported by the judge, if entries should be checked against it
+    pub standard: Option<CppStandard>,
+    /// What to do with entries unavailable under `standard`
+    pub unavailable: UnavailablePolicy,
+    /// Order of the pages
+    pub order: PrintOrder,
+    /// How downloaded assets are used
+    pub assets: AssetMode,
+}
+
+impl PrintOptions {
+    /// Create options using the default directory layout
+    ///
+    /// Markdown files are read from `./contents`, pages from `./cppreference_{lang}`,
+    /// and the result is written to `./cppreference_{lang}_print.html` (or
+    /// `./cppreference_{lang}_print_colored.html` if `colored` is set).
+    pub fn new(lang: &str, colored: bool) -> Self {
+        Config::default().print_options(lang, colored)
+    }
+}
+
 /// Concatenate HTML files for printing
 ///
 /// This function:
-/// 1. Checks if all required HTML files in `./cppreference` are present
+/// 1. Checks if all required pages are in the cache (migrating older layouts)
 /// 2. If not, errors out with details about missing files
-/// 3. If yes, concatenates them in sorted order by manipulating DOM elements
-/// 4. For non-colored output, flattens `pre` elements with class `de1`
-/// 5. Saves the result to the appropriate file
+/// 3. If yes, concatenates them in the selected order by manipulating DOM elements
+/// 4. Puts an index of all names and aliases in front, linking to the pages
+/// 5. Links the downloaded stylesheets and images relative to the output file,
+///    or embeds them, depending on `assets`
+/// 6. For non-colored output, flattens `pre` elements with class `de1`
+/// 7. Saves the result to the output file
+///
+/// If a standard is selected, entries unavailable under it are either left out
+/// or marked in the index and on their pages, depending on the policy.
+///
+/// In topic order, every Markdown file gets a title page and every section a
+/// heading listing the names documented in it. A reference documented in
+/// several places is printed once, under the place it first appears, and the
+/// other sections link to it.
 ///
 /// # Arguments
 ///
-/// * `colored` - Whether to include colored output (preserve syntax highlighting)
+/// * `options` - Directories, output file, language, color mode, standard and order
 ///
 /// # Returns
 ///
@@ -42,173 +161,196 @@ use crate::{
 /// # Errors
 ///
 /// Returns an error if:
-/// - The cppreference directory does not exist
+/// - There is no source for the language ([`AppError::UnknownLanguage`])
+/// - The input directory does not exist
 /// - Required HTML files are missing
 /// - File reading or writing fails
 /// - HTML parsing fails
-///
-/// # Output Files
-///
-/// - `./cppreference_print.html` - Flattened output (no syntax highlighting)
-/// - `./cppreference_print_colored.html` - Colored output (with syntax highlighting)
-pub fn print_references(colored: bool, lang: &str) -> Result<(), AppError> {
-    info!("Starting reference printer (language: {})", lang);
+pub fn print_references(options: &PrintOptions) -> Result<(), AppError> {
+    let source = source_for(&options.lang)?;
+    info!(
+        "Starting reference printer (source: {})",
+        source.description()
+    );
 
-    let cppreference_dir_name = format!("./cppreference_{}", lang);
-    let cppreference_dir = Path::new(&cppreference_dir_name);
+    let colored = options.colored;
+    let cppreference_dir = options.input_dir.as_path();
     if !cppreference_dir.exists() {
-        error!("{} directory does not exist", cppreference_dir_name);
+        error!("{:?} directory does not exist", cppreference_dir);
         return Err(AppError::IoError(std::io::Error::new(
             std::io::ErrorKind::NotFound,
-            format!("{} directory does not exist", cppreference_dir_name),
+            format!("{:?} directory does not exist", cppreference_dir),
         )));
     }
 
-    let unique_references = get_required_references()?;
+    let mut unique_references = get_required_references(&options.contents_dir)?;
+    let referenced_names: HashSet<String> = unique_references.keys().cloned().collect();
+    if let (Some(standard), UnavailablePolicy::Exclude) = (options.standard, options.unavailable) {
+        restrict_to_standard(&mut unique_references, standard);
+    }
 
     let required_names: HashSet<String> = unique_references.keys().cloned().collect();
 
     info!("Found {} required references", required_names.len());
 
-    // Get all HTML files in cppreference directory
-    let html_files: Vec<_> = fs::read_dir(cppreference_dir)?
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.path().extension().map_or(false, |ext| ext == "html"))
-        .map(|entry| entry.path())
-        .collect();
-
-    // Get the set of existing file names (without extension)
-    let existing_names: HashSet<String> = html_files
+    // Check for missing pages
+    let cache = PageCache::open(cppreference_dir)?;
+    let mut missing_files: Vec<_> = required_names
         .iter()
-        .filter_map(|path| {
-            path.file_stem()
-                .and_then(|stem| stem.to_str())
-                .map(|s| s.to_string())
-        })
-        .collect();
-
-    // Check for missing files
-    let missing_files: Vec<_> = required_names
-        .difference(&existing_names)
+        .filter(|name| !cache.contains(name))
         .cloned()
         .collect();
     if !missing_files.is_empty() {
+        missing_files.sort_by(|a, b| compare_cpp_names(a, b));
         error!("Missing required HTML files:");
         for name in &missing_files {
-            error!("  - {}.html", name);
+            error!("  - {} ({:?})", name, cache.page_path(name));
         }
         return Err(AppError::missing_files(&missing_files));
     }
+    let orphaned = cache
+        .manifest()
+        .pages
+        .keys()
+        .filter(|name| !referenced_names.contains(*name))
+        .count();
+    if orphaned > 0 {
+        info!(
+            "Ignoring {} cached page(s) no longer referenced (see `ref status` and `ref prune`)",
+            orphaned
+        );
+    }
 
-    // Filter HTML files to only include required ones, then sort
-    let mut sorted_files: Vec<_> = html_files
-        .into_iter()
-        .filter(|path| {
-            path.file_stem()
-                .and_then(|stem| stem.to_str())
-                .map(|s| required_names.contains(s))
-                .unwrap_or(false)
-        })
-        .collect();
-
-    // Sort files using recursive lexicographic order on :: split
-    sorted_files.sort_by(|a, b| {
-        let a_name = a.file_stem().and_then(|s| s.to_str()).unwrap_or("");
-        let b_name = b.file_stem().and_then(|s| s.to_str()).unwrap_or("");
-        compare_cpp_names(a_name, b_name)
-    });
+    // Sort pages by kind, then by name, then lay them out
+    let mut pages: Vec<&CppReference> = unique_references.values().collect();
+    pages.sort_by(|a, b| compare_references(a, b));
+    let items = layout_pages(&pages, options.order);
 
     // Process files by manipulating DOM elements
     let processed_content = {
-        // Create an iterator over the sorted files
-        let mut files_iter = sorted_files.into_iter();
-
-        if let Some(first_file) = files_iter.next() {
-            // Parse the first file as the root document
-            let root_html = Html::parse_document(&fs::read_to_string(first_file)?);
-            let tree_sink = HtmlTreeSink::new(root_html);
-
-            // Get the body element of the root document
-            let body_selector = Selector::parse("body").unwrap();
-            let body_id = {
-                let html_ref = tree_sink.0.borrow();
-                html_ref
-                    .select(&body_selector)
-                    .next()
-                    .map(|e| e.id())
-                    .ok_or_else(|| AppError::HtmlParsingError {
-                        file: "root document".to_string(),
-                        reason: "Could not find body element".to_string(),
-                    })
-            }?;
-
-            // Process remaining files
-            for file in files_iter {
-                // Parse the current file
-                let current_html = Html::parse_document(&fs::read_to_string(file)?);
-
-                // Get all elements from the current file's body
-                let current_body_selector = Selector::parse("body").unwrap();
-                if let Some(current_body) = current_html.select(&current_body_selector).next() {
-                    // Create a temporary container element
-                    let container_id = {
-                        let container_name =
-                            QualName::new(None, Default::default(), LocalName::from("div"));
-                        tree_sink.create_element(container_name, Vec::new(), Default::default())
-                    };
-
-                    // Add the container to the root body
-                    tree_sink.append(&body_id, NodeOrText::AppendNode(container_id));
-
-                    // Add all children of the current body to the container
-                    for child in current_body.children() {
-                        match *child.value() {
-                            scraper::node::Node::Element(_) => {
-                                // For elements, we need to recreate them in the tree sink
-                                if let Some(child_element) = scraper::ElementRef::wrap(child) {
-                                    add_element_to_tree(&tree_sink, &container_id, &child_element);
+        let Some(first_page) = pages.first() else {
+            // No files found
+            error!("No HTML files found in {:?} directory", cppreference_dir);
+            return Err(AppError::IoError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No HTML files found in {:?} directory", cppreference_dir),
+            )));
+        };
+
+        // Parse the first page as the root document
+        let first_file = cache.page_path(&first_page.key());
+        let root_html = Html::parse_document(&fs::read_to_string(first_file)?);
+        let tree_sink = HtmlTreeSink::new(root_html);
+
+        // Get the body element of the root document
+        let body_selector = Selector::parse("body").unwrap();
+        let body_id = {
+            let html_ref = tree_sink.0.borrow();
+            html_ref
+                .select(&body_selector)
+                .next()
+                .map(|e| e.id())
+                .ok_or_else(|| AppError::HtmlParsingError {
+                    file: "root document".to_string(),
+                    reason: "Could not find body element".to_string(),
+                })
+        }?;
+
+        // Move the content of the first page into its own container
+        let first_container_id = create_page_container(&tree_sink, 0, first_page, options.standard);
+        tree_sink.reparent_children(&body_id, &first_container_id);
+
+        // Put the index of all names and aliases in front of the pages
+        let index_id = create_index(&tree_sink, &pages, options.standard);
+        tree_sink.append(&body_id, NodeOrText::AppendNode(index_id));
+
+        for item in &items {
+            match *item {
+                PrintItem::Topic(ref title) => {
+                    let topic_id = create_element(
+                        &tree_sink,
+                        "div",
+                        &[
+                            ("class", "algcmp-topic"),
+                            ("style", "page-break-before: always"),
+                        ],
+                    );
+                    append_text_element(&tree_sink, &topic_id, "h1", title);
+                    tree_sink.append(&body_id, NodeOrText::AppendNode(topic_id));
+                }
+                PrintItem::Section {
+                    ref title,
+                    ref entries,
+                } => {
+                    let section_id = create_section(&tree_sink, title, entries, &pages);
+                    tree_sink.append(&body_id, NodeOrText::AppendNode(section_id));
+                }
+                PrintItem::Page(0) => {
+                    tree_sink.append(&body_id, NodeOrText::AppendNode(first_container_id));
+                }
+                PrintItem::Page(page) => {
+                    // Parse the current file
+                    let file = cache.page_path(&pages[page].key());
+                    let current_html = Html::parse_document(&fs::read_to_string(file)?);
+
+                    // Get all elements from the current file's body
+                    let current_body_selector = Selector::parse("body").unwrap();
+                    if let Some(current_body) = current_html.select(&current_body_selector).next() {
+                        // Create a container element, anchored for the index
+                        let container_id =
+                            create_page_container(&tree_sink, page, pages[page], options.standard);
+
+                        // Add the container to the root body
+                        tree_sink.append(&body_id, NodeOrText::AppendNode(container_id));
+
+                        // Add all children of the current body to the container
+                        for child in current_body.children() {
+                            match *child.value() {
+                                scraper::node::Node::Element(_) => {
+                                    // For elements, we need to recreate them in the tree sink
+                                    if let Some(child_element) = scraper::ElementRef::wrap(child) {
+                                        add_element_to_tree(
+                                            &tree_sink,
+                                            &container_id,
+                                            &child_element,
+                                        );
+                                    }
+                                }
+                                scraper::node::Node::Text(ref text_node) => {
+                                    // For text nodes, add directly
+                                    let mut tendril = StrTendril::new();
+                                    tendril.push_slice(&text_node.text);
+                                    tree_sink
+                                        .append(&container_id, NodeOrText::AppendText(tendril));
+                                }
+                                _ => {
+                                    // Skip other node types
                                 }
-                            }
-                            scraper::node::Node::Text(ref text_node) => {
-                                // For text nodes, add directly
-                                let mut tendril = StrTendril::new();
-                                tendril.push_slice(&text_node.text);
-                                tree_sink.append(&container_id, NodeOrText::AppendText(tendril));
-                            }
-                            _ => {
-                                // Skip other node types
                             }
                         }
                     }
                 }
             }
+        }
 
-            // Convert back to HTML string
-            let root_html = tree_sink.0.into_inner();
-            let concatenated_content = root_html.html();
+        // Convert back to HTML string
+        let root_html = tree_sink.0.into_inner();
+        let concatenated_content = resolve_assets(
+            &root_html.html(),
+            &cache,
+            &options.output_file,
+            options.assets,
+        )?;
 
-            // Process content if not colored
-            if colored {
-                concatenated_content
-            } else {
-                flatten_code_blocks(&concatenated_content)?
-            }
+        // Process content if not colored
+        if colored {
+            concatenated_content
         } else {
-            // No files found
-            error!("No HTML files found in {} directory", cppreference_dir_name);
-            return Err(AppError::IoError(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("No HTML files found in {} directory", cppreference_dir_name),
-            )));
+            flatten_code_blocks(&concatenated_content)?
         }
     };
 
-    let output_file_name = if colored {
-        format!("./cppreference_{}_print_colored.html", lang)
-    } else {
-        format!("./cppreference_{}_print.html", lang)
-    };
-    let output_file = Path::new(&output_file_name);
+    let output_file = options.output_file.as_path();
 
     fs::write(output_file, processed_content)?;
     info!("Saved concatenated references to {:?}", output_file);
@@ -216,6 +358,383 @@ pub fn print_references(colored: bool, lang: &str) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Point the links to downloaded assets to the cache, or embed the assets
+///
+/// Pages link to their assets relative to the cache directory (`assets/...`).
+/// With [`AssetMode::Link`] these links are made relative to the output file;
+/// with [`AssetMode::Inline`] they are replaced by `data:` URLs, with the
+/// fonts and images of stylesheets embedded as well. Assets missing from the
+/// cache are left alone with a warning.
+fn resolve_assets(
+    content: &str,
+    cache: &PageCache,
+    output_file: &Path,
+    mode: AssetMode,
+) -> Result<String, AppError> {
+    let prefix = format!("{}/", ASSETS_DIR_NAME);
+    let assets_dir = cache.assets_dir();
+    let asset_file = |url: &str| {
+        url.strip_prefix(&prefix)
+            .filter(|file| !file.contains(['/', '\\']))
+            .map(str::to_string)
+    };
+    match mode {
+        AssetMode::Link => {
+            let output_dir = output_file
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let assets_url = relative_url(output_dir, &assets_dir)?;
+            Ok(rewrite_asset_urls(content, |url| {
+                asset_file(url).map(|file| format!("{}/{}", assets_url, file))
+            }))
+        }
+        AssetMode::Inline => Ok(rewrite_asset_urls(content, |url| {
+            inline_asset(&assets_dir, &asset_file(url)?)
+        })),
+    }
+}
+
+/// A `data:` URL embedding an asset, with the assets of a stylesheet embedded as well
+///
+/// # Returns
+///
+/// The URL, or `None` if the file cannot be read.
+fn inline_asset(assets_dir: &Path, file: &str) -> Option<String> {
+    let path = assets_dir.join(file);
+    let content = match fs::read(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            warn!("Cannot embed asset {:?}: {}", path, e);
+            return None;
+        }
+    };
+    if !file.ends_with(".css") {
+        return Some(data_url(file, &content));
+    }
+
+    // Assets of a stylesheet are stored next to it and linked by file name
+    let css = rewrite_css_urls(&String::from_utf8_lossy(&content), |url| {
+        if url.contains(['/', '\\', ':']) {
+            None
+        } else {
+            inline_asset(assets_dir, url)
+        }
+    });
+    Some(data_url(file, css.as_bytes()))
+}
+
+/// A piece of the printout, in output order
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PrintItem {
+    /// Title page of a Markdown file
+    Topic(String),
+    /// Heading of a section, listing the pages documented in it
+    Section { title: String, entries: Vec<usize> },
+    /// The page with the given index in name order
+    Page(usize),
+}
+
+/// A Markdown file while laying out pages by topic
+struct TopicLayout<'a> {
+    title: String,
+    sections: Vec<SectionLayout<'a>>,
+}
+
+/// A section of a Markdown file while laying out pages by topic
+struct SectionLayout<'a> {
+    headings: &'a [String],
+    line: usize,
+    /// Every page documented in the section
+    entries: Vec<usize>,
+    /// The pages printed in the section
+    pages: Vec<usize>,
+}
+
+/// Lay out the pages of the printout
+///
+/// # Arguments
+///
+/// * `pages` - The references sorted by name
+/// * `order` - The order to print them in
+///
+/// # Returns
+///
+/// The items of the printout. Every page appears exactly once.
+fn layout_pages(pages: &[&CppReference], order: PrintOrder) -> Vec<PrintItem> {
+    match order {
+        PrintOrder::Name => (0..pages.len()).map(PrintItem::Page).collect(),
+        PrintOrder::Source => {
+            // Sorting is stable, so references from the same link keep name order
+            let mut indices: Vec<usize> = (0..pages.len()).collect();
+            indices.sort_by_key(|&page| {
+                let source = pages[page].sources.first();
+                (
+                    source.is_none(),
+                    source.map(|source| (&source.file, source.line, source.column)),
+                )
+            });
+            indices.into_iter().map(PrintItem::Page).collect()
+        }
+        PrintOrder::Topic => layout_by_topic(pages),
+    }
+}
+
+/// Lay out the pages grouped by Markdown file and section
+///
+/// Each page is printed in the section of its first source. References
+/// without a source are printed at the end.
+fn layout_by_topic(pages: &[&CppReference]) -> Vec<PrintItem> {
+    let mut topics: BTreeMap<&Path, TopicLayout> = BTreeMap::new();
+    let mut unsourced = Vec::new();
+
+    for (page, reference) in pages.iter().enumerate() {
+        if reference.sources.is_empty() {
+            unsourced.push(page);
+        }
+        for (i, source) in reference.sources.iter().enumerate() {
+            let topic = topics
+                .entry(source.file.as_path())
+                .or_insert_with(|| TopicLayout {
+                    title: source.title.clone().unwrap_or_else(|| {
+                        source
+                            .file
+                            .file_stem()
+                            .map(|stem| stem.to_string_lossy().into_owned())
+                            .unwrap_or_default()
+                    }),
+                    sections: Vec::new(),
+                });
+            let position = topic
+                .sections
+                .iter()
+                .position(|section| section.headings == source.headings.as_slice())
+                .unwrap_or_else(|| {
+                    topic.sections.push(SectionLayout {
+                        headings: &source.headings,
+                        line: source.line,
+                        entries: Vec::new(),
+                        pages: Vec::new(),
+                    });
+                    topic.sections.len() - 1
+                });
+            let section = &mut topic.sections[position];
+            section.line = section.line.min(source.line);
+            if !section.entries.contains(&page) {
+                section.entries.push(page);
+            }
+            if i == 0 {
+                section.pages.push(page);
+            }
+        }
+    }
+
+    let mut items = Vec::new();
+    for topic in topics.into_values() {
+        items.push(PrintItem::Topic(topic.title));
+        let mut sections = topic.sections;
+        sections.sort_by_key(|section| section.line);
+        for section in sections {
+            if !section.headings.is_empty() {
+                items.push(PrintItem::Section {
+                    title: section.headings.join(" › "),
+                    entries: section.entries,
+                });
+            }
+            items.extend(section.pages.into_iter().map(PrintItem::Page));
+        }
+    }
+    items.extend(unsourced.into_iter().map(PrintItem::Page));
+    items
+}
+
+/// The anchor id of the `page`-th page in the printout
+fn page_anchor(page: usize) -> String {
+    format!("algcmp-page-{}", page)
+}
+
+/// Create a detached element with the given attributes
+fn create_element(
+    tree_sink: &HtmlTreeSink,
+    name: &str,
+    attributes: &[(&str, &str)],
+) -> <HtmlTreeSink as TreeSink>::Handle {
+    let element_name = QualName::new(None, Default::default(), LocalName::from(name));
+    let attrs = attributes
+        .iter()
+        .map(|(name, value)| Attribute {
+            name: QualName::new(None, Default::default(), LocalName::from(*name)),
+            value: StrTendril::from(*value),
+        })
+        .collect();
+    tree_sink.create_element(element_name, attrs, Default::default())
+}
+
+/// Create the index of the printout
+///
+/// The index lists every name and alias in sorted order, each linking to the
+/// page documenting it. Aliases also mention the name of that page, so that
+/// e.g. `std::scientific` can be found even though it is printed on the
+/// `std::fixed` page. Names of macros, keywords, language and C pages are
+/// sorted after namespace entities and labelled with their kind. Names
+/// unavailable under `standard` are marked with the standard they require.
+///
+/// # Arguments
+///
+/// * `tree_sink` - The HtmlTreeSink to create the index in
+/// * `pages` - The references in page order
+/// * `standard` - The standard supported by the judge, if any
+///
+/// # Returns
+///
+/// The detached index element.
+fn create_index(
+    tree_sink: &HtmlTreeSink,
+    pages: &[&CppReference],
+    standard: Option<CppStandard>,
+) -> <HtmlTreeSink as TreeSink>::Handle {
+    let mut entries: Vec<(&str, Option<CppStandard>, usize)> = pages
+        .iter()
+        .enumerate()
+        .flat_map(|(page, reference)| {
+            reference
+                .names_with_standards()
+                .map(move |(name, since)| (name, since, page))
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        pages[a.2]
+            .kind
+            .cmp(&pages[b.2].kind)
+            .then_with(|| compare_cpp_names(a.0, b.0))
+    });
+
+    let index_id = create_element(tree_sink, "div", &[("class", "algcmp-index")]);
+    append_text_element(tree_sink, &index_id, "h1", "Index");
+
+    let list_id = create_element(tree_sink, "ul", &[]);
+    for (name, since, page) in entries {
+        let item_id = create_element(tree_sink, "li", &[]);
+        let href = format!("#{}", page_anchor(page));
+        let link_id = create_element(tree_sink, "a", &[("href", &href)]);
+        tree_sink.append(&link_id, NodeOrText::AppendText(StrTendril::from(name)));
+        tree_sink.append(&item_id, NodeOrText::AppendNode(link_id));
+
+        let page_name = &pages[page].name;
+        if name != page_name {
+            let see_also = format!(" (see {})", pages[page].display_name());
+            tree_sink.append(&item_id, NodeOrText::AppendText(StrTendril::from(see_also)));
+        } else if let Some(label) = pages[page].kind.label() {
+            let label = format!(" ({})", label);
+            tree_sink.append(&item_id, NodeOrText::AppendText(StrTendril::from(label)));
+        }
+        if let (Some(since), Some(standard)) = (since, standard)
+            && since > standard
+        {
+            let flag = format!(" [{}]", since);
+            tree_sink.append(&item_id, NodeOrText::AppendText(StrTendril::from(flag)));
+        }
+        tree_sink.append(&list_id, NodeOrText::AppendNode(item_id));
+    }
+    tree_sink.append(&index_id, NodeOrText::AppendNode(list_id));
+
+    index_id
+}
+
+/// Create the anchored container holding one page
+///
+/// The container starts with a banner if the page is unavailable under the
+/// selected standard.
+fn create_page_container(
+    tree_sink: &HtmlTreeSink,
+    page: usize,
+    reference: &CppReference,
+    standard: Option<CppStandard>,
+) -> <HtmlTreeSink as TreeSink>::Handle {
+    let container_id = create_element(tree_sink, "div", &[("id", &page_anchor(page))]);
+    if let Some(banner_id) = create_unavailable_banner(tree_sink, reference, standard) {
+        tree_sink.append(&container_id, NodeOrText::AppendNode(banner_id));
+    }
+    container_id
+}
+
+/// Append an element containing only text
+fn append_text_element(
+    tree_sink: &HtmlTreeSink,
+    parent_id: &<HtmlTreeSink as TreeSink>::Handle,
+    name: &str,
+    text: &str,
+) {
+    let element_id = create_element(tree_sink, name, &[]);
+    tree_sink.append(&element_id, NodeOrText::AppendText(StrTendril::from(text)));
+    tree_sink.append(parent_id, NodeOrText::AppendNode(element_id));
+}
+
+/// Create the heading of a section, listing the names documented in it
+///
+/// Every name links to its page, which may be printed under another section
+/// if the reference appears in several places.
+fn create_section(
+    tree_sink: &HtmlTreeSink,
+    title: &str,
+    entries: &[usize],
+    pages: &[&CppReference],
+) -> <HtmlTreeSink as TreeSink>::Handle {
+    let section_id = create_element(tree_sink, "div", &[("class", "algcmp-section")]);
+    append_text_element(tree_sink, &section_id, "h2", title);
+
+    let list_id = create_element(tree_sink, "ul", &[]);
+    for &page in entries {
+        let item_id = create_element(tree_sink, "li", &[]);
+        let href = format!("#{}", page_anchor(page));
+        let link_id = create_element(tree_sink, "a", &[("href", &href)]);
+        tree_sink.append(
+            &link_id,
+            NodeOrText::AppendText(StrTendril::from(pages[page].display_name())),
+        );
+        tree_sink.append(&item_id, NodeOrText::AppendNode(link_id));
+        tree_sink.append(&list_id, NodeOrText::AppendNode(item_id));
+    }
+    tree_sink.append(&section_id, NodeOrText::AppendNode(list_id));
+
+    section_id
+}
+
+/// Create the banner marking a page unavailable under the selected standard
+///
+/// # Returns
+///
+/// The detached banner element, or `None` if no standard is selected or the
+/// page is available under it.
+fn create_unavailable_banner(
+    tree_sink: &HtmlTreeSink,
+    reference: &CppReference,
+    standard: Option<CppStandard>,
+) -> Option<<HtmlTreeSink as TreeSink>::Handle> {
+    let standard = standard?;
+    if reference.is_available(standard) {
+        return None;
+    }
+
+    let text = match reference.minimum_standard() {
+        Some(since) => format!("Requires {} (not available in {})", since, standard),
+        None => format!("Not available in {}", standard),
+    };
+    let banner_id = create_element(
+        tree_sink,
+        "div",
+        &[
+            ("class", "algcmp-unavailable"),
+            (
+                "style",
+                "border: 2px solid; font-weight: bold; padding: 4px",
+            ),
+        ],
+    );
+    tree_sink.append(&banner_id, NodeOrText::AppendText(StrTendril::from(text)));
+    Some(banner_id)
+}
+
 /// Recursively add an element and its children to the tree sink
 ///
 /// This function creates a deep copy of an element and all its children
@@ -273,3 +792,106 @@ fn add_element_to_tree(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::references::Provenance;
+
+    fn reference(name: &str, sources: &[(&str, &[&str], usize)]) -> CppReference {
+        CppReference {
+            name: name.to_string(),
+            sources: sources
+                .iter()
+                .map(|&(file, headings, line)| Provenance {
+                    file: PathBuf::from(file),
+                    title: None,
+                    headings: headings.iter().map(|h| h.to_string()).collect(),
+                    line,
+                    column: 8,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_layout_pages() {
+        let find = reference("std::find", &[("b/Search.md", &["Functions"], 9)]);
+        let sort = reference(
+            "std::sort",
+            &[
+                ("a/Sorting.md", &["Functions"], 12),
+                ("b/Search.md", &["Functions"], 7),
+            ],
+        );
+        let vector = reference("std::vector", &[("a/Sorting.md", &["Containers"], 5)]);
+        let pages = vec![&find, &sort, &vector];
+
+        assert_eq!(
+            layout_pages(&pages, PrintOrder::Name),
+            vec![PrintItem::Page(0), PrintItem::Page(1), PrintItem::Page(2)]
+        );
+        assert_eq!(
+            layout_pages(&pages, PrintOrder::Source),
+            vec![PrintItem::Page(2), PrintItem::Page(1), PrintItem::Page(0)]
+        );
+        assert_eq!(
+            layout_pages(&pages, PrintOrder::Topic),
+            vec![
+                PrintItem::Topic("Sorting".to_string()),
+                PrintItem::Section {
+                    title: "Containers".to_string(),
+                    entries: vec![2],
+                },
+                PrintItem::Page(2),
+                PrintItem::Section {
+                    title: "Functions".to_string(),
+                    entries: vec![1],
+                },
+                PrintItem::Page(1),
+                PrintItem::Topic("Search".to_string()),
+                PrintItem::Section {
+                    title: "Functions".to_string(),
+                    entries: vec![0, 1],
+                },
+                PrintItem::Page(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_assets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache_dir = temp_dir.path().join("cppreference_en");
+        fs::create_dir_all(cache_dir.join(ASSETS_DIR_NAME)).unwrap();
+        fs::write(
+            cache_dir.join("assets/0a.css"),
+            "@font-face { src: url(1b.woff2) }",
+        )
+        .unwrap();
+        fs::write(cache_dir.join("assets/1b.woff2"), b"wOF2").unwrap();
+        let cache = PageCache::open(&cache_dir).unwrap();
+
+        let html = r#"<!DOCTYPE html><html><head><link href="assets/0a.css" rel="stylesheet"></head><body><img src="assets/9z.png"><img src="https://example.com/a.png"></body></html>"#;
+        let output_file = temp_dir.path().join("out/print.html");
+
+        let linked = resolve_assets(html, &cache, &output_file, AssetMode::Link).unwrap();
+        assert!(linked.contains(r#"href="../cppreference_en/assets/0a.css""#));
+        assert!(linked.contains(r#"src="../cppreference_en/assets/9z.png""#));
+        assert!(linked.contains(r#"src="https://example.com/a.png""#));
+
+        let inlined = resolve_assets(html, &cache, &output_file, AssetMode::Inline).unwrap();
+        let css = data_url(
+            "0a.css",
+            format!(
+                "@font-face {{ src: url({}) }}",
+                data_url("1b.woff2", b"wOF2")
+            )
+            .as_bytes(),
+        );
+        assert!(inlined.contains(&format!(r#"href="{}""#, css)));
+        assert!(inlined.contains(r#"src="assets/9z.png""#));
+    }
+}
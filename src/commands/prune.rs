@@ -0,0 +1,262 @@
+//! Prune command implementation
+//!
+//! This module removes orphaned pages from a cache directory: cached pages
+//! that no Markdown file refers to anymore, e.g. after a link was corrected or
+//! a row removed. [`print_references`](super::print::print_references) ignores
+//! them, but they keep taking space and show up in the manifest.
+//!
+//! Orphaned pages are either deleted or moved to an archive directory, which
+//! is laid out like a cache directory so that its pages can be moved back.
+
+use log::{debug, info};
+use std::{fs, path::Path, path::PathBuf};
+
+use crate::{
+    cache::{PageCache, PageMetadata},
+    config::Config,
+    errors::AppError,
+    references::{compare_cpp_names, get_required_references},
+};
+
+/// Options for [`orphaned_pages`] and [`prune_references`]
+#[derive(Debug, Clone)]
+pub struct PruneOptions {
+    /// Directory containing the Markdown files with C++ reference links
+    pub contents_dir: PathBuf,
+    /// The cache directory to prune
+    pub cache_dir: PathBuf,
+    /// Directory to move the pruned pages to instead of deleting them
+    pub archive: Option<PathBuf>,
+}
+
+impl PruneOptions {
+    /// Create options using the default directory layout
+    ///
+    /// Orphaned pages in `./cppreference_{lang}` are deleted.
+    pub fn new(lang: &str) -> Self {
+        Config::default().prune_options(lang)
+    }
+}
+
+/// Find the cached pages that are not referenced by the Markdown files
+///
+/// The cache directory is only read, not migrated (see [`PageCache::load`]).
+///
+/// # Arguments
+///
+/// * `options` - Contents and cache directories
+///
+/// # Returns
+///
+/// The names of the orphaned pages, sorted by name. A cache directory that
+/// does not exist has no orphaned pages.
+///
+/// # Errors
+///
+/// Returns an error if reference extraction fails or the cache cannot be read.
+pub fn orphaned_pages(options: &PruneOptions) -> Result<Vec<String>, AppError> {
+    let references = get_required_references(&options.contents_dir)?;
+    if !options.cache_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let cache = PageCache::load(&options.cache_dir)?;
+    let mut names: Vec<String> = cache
+        .manifest()
+        .pages
+        .keys()
+        .filter(|name| !references.contains_key(*name))
+        .cloned()
+        .collect();
+    names.sort_by(|a, b| compare_cpp_names(a, b));
+    Ok(names)
+}
+
+/// Delete or archive cached pages
+///
+/// The processed page, its raw copy and its manifest entry are removed. With
+/// [`PruneOptions::archive`], the files are moved to the archive directory and
+/// recorded in its manifest instead of being deleted. Shared assets are kept.
+///
+/// # Arguments
+///
+/// * `options` - The cache directory and where to archive the pages
+/// * `names` - The pages to prune, usually found with [`orphaned_pages`]
+///
+/// # Returns
+///
+/// The number of pages pruned; names that are not cached are ignored.
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be removed or moved, or a manifest
+/// cannot be read or written. The pages pruned before the error are recorded
+/// in the manifests.
+pub fn prune_references(options: &PruneOptions, names: &[String]) -> Result<usize, AppError> {
+    let mut cache = PageCache::open(&options.cache_dir)?;
+    let mut archive = match &options.archive {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            Some(PageCache::open(dir)?)
+        }
+        None => None,
+    };
+
+    let mut pruned = 0;
+    for name in names {
+        let Some(entry) = cache.entry(name).cloned() else {
+            debug!("{} is not cached, skipping", name);
+            continue;
+        };
+        let result = match &mut archive {
+            Some(archive) => archive_page(&cache, archive, name, entry.metadata),
+            None => delete_page(&cache, name),
+        };
+        if let Err(e) = result {
+            // Record the pages pruned so far, so that no archived page goes
+            // unrecorded
+            if let Some(archive) = &archive {
+                archive.save()?;
+            }
+            cache.save()?;
+            return Err(e);
+        }
+        cache.remove(name);
+        pruned += 1;
+    }
+
+    // Save the archive first, so that no archived page goes unrecorded
+    if let Some(archive) = &archive {
+        archive.save()?;
+    }
+    cache.save()?;
+    info!("Pruned {} page(s) from {:?}", pruned, options.cache_dir);
+    Ok(pruned)
+}
+
+/// Move the files of a page to the archive and record it there
+///
+/// A page whose file is already gone is not archived.
+fn archive_page(
+    cache: &PageCache,
+    archive: &mut PageCache,
+    name: &str,
+    mut metadata: PageMetadata,
+) -> Result<(), AppError> {
+    let (page_path, raw_path) = (cache.page_path(name), cache.raw_path(name));
+    if !page_path.is_file() {
+        debug!("{} is already gone, not archiving it", name);
+        return Ok(());
+    }
+    move_file(&page_path, &archive.page_path(name))?;
+    let raw_sha256 = metadata.raw_sha256.take();
+    archive.insert(name, metadata.clone());
+    if raw_path.is_file() {
+        let archived_raw = archive.raw_path(name);
+        fs::create_dir_all(archived_raw.parent().unwrap_or(archive.dir()))?;
+        move_file(&raw_path, &archived_raw)?;
+        metadata.raw_sha256 = raw_sha256;
+        archive.insert(name, metadata);
+    }
+    debug!("Archived {} to {:?}", name, archive.dir());
+    Ok(())
+}
+
+/// Delete the files of a page
+///
+/// A file that is already gone is skipped.
+fn delete_page(cache: &PageCache, name: &str) -> Result<(), AppError> {
+    for path in [cache.page_path(name), cache.raw_path(name)] {
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    debug!("Deleted {}", name);
+    Ok(())
+}
+
+/// Move a file, copying it if it cannot be renamed (e.g. to another file system)
+fn move_file(from: &Path, to: &Path) -> Result<(), AppError> {
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache::file_name_for, commands::download::test_server::write_contents};
+
+    #[test]
+    fn test_prune_references() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let contents_dir = write_contents(temp_dir.path(), &[("std::sort", "cpp/algorithm/sort")]);
+
+        let cache_dir = temp_dir.path().join("cppreference_en");
+        fs::create_dir(&cache_dir).unwrap();
+        let mut cache = PageCache::open(&cache_dir).unwrap();
+        for name in ["std::sort", "std::count", "std::fill", "std::vector<bool>"] {
+            fs::write(cache.page_path(name), name).unwrap();
+            let raw_sha256 = cache.write_raw(name, name).unwrap();
+            cache.insert(
+                name,
+                PageMetadata {
+                    raw_sha256: Some(raw_sha256),
+                    ..PageMetadata::default()
+                },
+            );
+        }
+        cache.save().unwrap();
+        let (fill_page, fill_raw) = (cache.page_path("std::fill"), cache.raw_path("std::fill"));
+
+        let mut options = PruneOptions::new("en");
+        options.contents_dir = contents_dir;
+        options.cache_dir = cache_dir.clone();
+        let orphaned = orphaned_pages(&options).unwrap();
+        assert_eq!(orphaned, ["std::count", "std::fill", "std::vector<bool>"]);
+
+        // Archive one page and delete the others
+        options.archive = Some(temp_dir.path().join("archive"));
+        assert_eq!(
+            prune_references(&options, &["std::fill".to_string()]).unwrap(),
+            1
+        );
+        assert!(!fill_page.exists() && !fill_raw.exists());
+        let archive = PageCache::open(&temp_dir.path().join("archive")).unwrap();
+        assert_eq!(
+            fs::read_to_string(archive.page_path("std::fill")).unwrap(),
+            "std::fill"
+        );
+        assert!(archive.raw_path("std::fill").is_file());
+        assert!(
+            archive
+                .entry("std::fill")
+                .unwrap()
+                .metadata
+                .raw_sha256
+                .is_some()
+        );
+
+        // A page that cannot be archived stops pruning, but the pages archived
+        // before it stay recorded
+        let blocked = temp_dir.path().join("blocked");
+        fs::create_dir_all(blocked.join(file_name_for("std::sort"))).unwrap();
+        options.archive = Some(blocked.clone());
+        let names = ["std::count".to_string(), "std::sort".to_string()];
+        assert!(prune_references(&options, &names).is_err());
+        let archive = PageCache::open(&blocked).unwrap();
+        assert!(archive.contains("std::count"));
+        assert!(PageCache::open(&cache_dir).unwrap().contains("std::sort"));
+
+        options.archive = None;
+        assert_eq!(prune_references(&options, &orphaned).unwrap(), 1);
+        let cache = PageCache::open(&cache_dir).unwrap();
+        assert_eq!(
+            cache.manifest().pages.keys().collect::<Vec<_>>(),
+            ["std::sort"]
+        );
+        assert!(orphaned_pages(&options).unwrap().is_empty());
+    }
+}
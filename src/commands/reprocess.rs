@@ -0,0 +1,260 @@
+//! Reprocess command implementation
+//!
+//! This module regenerates the processed pages of a cache directory from the
+//! pages as served, which are kept in its `raw` subdirectory (see
+//! [`crate::cache`]). After the processing changes, cached pages can be
+//! brought up to date without downloading them again; every page is recorded
+//! with the [`PROCESSING_VERSION`] that produced it and whether its assets are
+//! linked.
+
+use log::{debug, error, info, warn};
+use std::{fs, path::PathBuf};
+
+use super::{
+    download::{DownloadStatus, DownloadSummary, process_page},
+    import::ARCHIVE_NAVIGATION_SELECTORS,
+};
+use crate::{
+    cache::{PageCache, sha256_hex},
+    config::Config,
+    errors::AppError,
+    html::PROCESSING_VERSION,
+    sources::source_for,
+};
+
+/// Options for [`reprocess_references`]
+#[derive(Debug, Clone)]
+pub struct ReprocessOptions {
+    /// The cache directory whose pages are reprocessed
+    pub cache_dir: PathBuf,
+    /// Language version, selecting the navigation elements that are removed
+    pub lang: String,
+    /// Whether to link the cached stylesheets and images
+    pub assets: bool,
+    /// Whether to only reprocess pages produced by an older processing version
+    pub outdated: bool,
+}
+
+impl ReprocessOptions {
+    /// Create options using the default directory layout
+    ///
+    /// Pages in `./cppreference_{lang}` are reprocessed, all of them, and
+    /// linked to the cached assets.
+    pub fn new(lang: &str) -> Self {
+        Config::default().reprocess_options(lang)
+    }
+}
+
+/// Regenerate the processed pages of a cache from the pages as served
+///
+/// Every page with a raw copy goes through the same processing as a freshly
+/// downloaded page (see [`process_page`]); imported pages lose the navigation
+/// elements of the offline archive instead. A page is only rewritten if its
+/// processed content changed.
+///
+/// # Arguments
+///
+/// * `options` - The cache directory, language and what to reprocess
+///
+/// # Returns
+///
+/// The outcome of every page: `succeeded` if it was rewritten, `unchanged`,
+/// `skipped` if it has no raw copy (it was cached by an older version and must
+/// be downloaded again) or is up to date with `outdated`, and `failed` if its
+/// raw copy cannot be read or processed.
+///
+/// # Errors
+///
+/// Returns an error if there is no source for the language, or the cache
+/// cannot be read or written.
+pub fn reprocess_references(options: &ReprocessOptions) -> Result<DownloadSummary, AppError> {
+    let source = source_for(&options.lang)?;
+    let mut cache = PageCache::open(&options.cache_dir)?;
+    let mut summary = DownloadSummary::default();
+
+    let mut entries: Vec<_> = cache
+        .manifest()
+        .pages
+        .iter()
+        .map(|(name, entry)| (name.clone(), entry.metadata.clone()))
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    info!(
+        "Reprocessing {} page(s) in {:?}",
+        entries.len(),
+        options.cache_dir
+    );
+
+    for (name, mut metadata) in entries {
+        if options.outdated && metadata.is_current(options.assets) {
+            debug!("{} is up to date, skipping", name);
+            summary.push(&name, DownloadStatus::Skipped, None);
+            continue;
+        }
+        let raw_path = cache.raw_path(&name);
+        if metadata.raw_sha256.is_none() || !raw_path.is_file() {
+            warn!(
+                "{} has no raw copy, download it again to reprocess it",
+                name
+            );
+            summary.push(
+                &name,
+                DownloadStatus::Skipped,
+                Some("no raw copy, download it again with --overwrite".to_string()),
+            );
+            continue;
+        }
+
+        let raw = match fs::read_to_string(&raw_path) {
+            Ok(raw) => raw,
+            Err(e) => {
+                error!("Failed to read the raw copy of {}: {}", name, e);
+                summary.push(&name, DownloadStatus::Failed, Some(e.to_string()));
+                continue;
+            }
+        };
+        let selectors: &[&str] = if metadata.archive.is_some() {
+            &ARCHIVE_NAVIGATION_SELECTORS
+        } else {
+            source.navigation_selectors()
+        };
+        let page_url = metadata
+            .final_url
+            .clone()
+            .or_else(|| metadata.url.clone())
+            .unwrap_or_default();
+        let content = match process_page(&cache, &raw, &name, &page_url, selectors, options.assets)
+        {
+            Ok(content) => content,
+            Err(e) => {
+                error!("Failed to reprocess {}: {}", name, e);
+                summary.push(&name, DownloadStatus::Failed, Some(e.to_string()));
+                continue;
+            }
+        };
+
+        let output_path = cache.page_path(&name);
+        let sha256 = sha256_hex(content.as_bytes());
+        let unchanged = output_path.is_file()
+            && metadata.sha256.as_ref() == Some(&sha256)
+            && metadata.is_current(options.assets);
+        if !unchanged {
+            fs::write(&output_path, &content)?;
+            debug!("Reprocessed {} to {:?}", name, output_path);
+        }
+        metadata.sha256 = Some(sha256);
+        metadata.processing_version = Some(PROCESSING_VERSION);
+        metadata.assets_linked = options.assets;
+        cache.insert(&name, metadata);
+        summary.push(
+            &name,
+            if unchanged {
+                DownloadStatus::Unchanged
+            } else {
+                DownloadStatus::Succeeded
+            },
+            None,
+        );
+    }
+    cache.save()?;
+
+    summary.sort();
+    info!("Reprocessing completed: {}", summary.totals());
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::PageMetadata;
+
+    #[test]
+    fn test_reprocess_references() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let raw = r#"<html><head></head><body><div id="mw-head">head</div><div class="t-navbar">nav</div><p>sort</p></body></html>"#;
+
+        let mut cache = PageCache::open(dir).unwrap();
+        for (name, archive) in [
+            ("std::sort", None),
+            ("std::find", Some("reference/en/find.html")),
+        ] {
+            fs::write(cache.page_path(name), raw).unwrap();
+            let raw_sha256 = cache.write_raw(name, raw).unwrap();
+            cache.insert(
+                name,
+                PageMetadata {
+                    raw_sha256: Some(raw_sha256),
+                    archive: archive.map(str::to_string),
+                    processing_version: Some(1),
+                    ..PageMetadata::default()
+                },
+            );
+        }
+        fs::write(cache.page_path("std::count"), raw).unwrap();
+        cache.insert("std::count", PageMetadata::default());
+        cache.save().unwrap();
+
+        let mut options = ReprocessOptions::new("en");
+        options.cache_dir = dir.to_path_buf();
+        let summary = reprocess_references(&options).unwrap();
+        let outcome: Vec<_> = summary
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.status))
+            .collect();
+        assert_eq!(
+            outcome,
+            [
+                ("std::count", DownloadStatus::Skipped),
+                ("std::find", DownloadStatus::Succeeded),
+                ("std::sort", DownloadStatus::Succeeded),
+            ]
+        );
+
+        let cache = PageCache::open(dir).unwrap();
+        let sort = fs::read_to_string(cache.page_path("std::sort")).unwrap();
+        assert!(!sort.contains("mw-head") && !sort.contains("t-navbar"));
+        // Imported pages only lose the navigation bar of the archive
+        let find = fs::read_to_string(cache.page_path("std::find")).unwrap();
+        assert!(find.contains("mw-head") && !find.contains("t-navbar"));
+        assert_eq!(
+            cache
+                .entry("std::sort")
+                .unwrap()
+                .metadata
+                .processing_version,
+            Some(PROCESSING_VERSION)
+        );
+
+        // An unreadable raw copy fails without stopping the others
+        let find_raw = cache.raw_path("std::find");
+        fs::write(&find_raw, [0xff, 0xfe]).unwrap();
+        let summary = reprocess_references(&options).unwrap();
+        assert_eq!(summary.count(DownloadStatus::Failed), 1);
+        assert_eq!(summary.count(DownloadStatus::Unchanged), 1);
+        fs::write(&find_raw, raw).unwrap();
+
+        // Nothing changes the second time
+        options.outdated = true;
+        let summary = reprocess_references(&options).unwrap();
+        assert_eq!(summary.count(DownloadStatus::Skipped), 3);
+        options.outdated = false;
+        let summary = reprocess_references(&options).unwrap();
+        assert_eq!(summary.count(DownloadStatus::Unchanged), 2);
+
+        // Pages whose assets were linked are outdated once assets are not
+        options.outdated = true;
+        options.assets = false;
+        let summary = reprocess_references(&options).unwrap();
+        assert_eq!(summary.count(DownloadStatus::Skipped), 1);
+        let cache = PageCache::open(dir).unwrap();
+        assert!(!cache.entry("std::sort").unwrap().metadata.assets_linked);
+
+        options.lang = "xx".to_string();
+        assert!(matches!(
+            reprocess_references(&options),
+            Err(AppError::UnknownLanguage { .. })
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+//! Status command implementation
+//!
+//! This module compares the page caches with the references in the Markdown
+//! files. For every language it counts the required references, those that
+//! are cached (present) or not (missing), cached pages that need to be
+//! processed or downloaded again (stale), and cached pages no Markdown file
+//! refers to anymore (orphaned, see [`super::prune`]).
+
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    cache::PageCache,
+    commands::OutputFormat,
+    config::Config,
+    errors::AppError,
+    references::{CppReference, compare_cpp_names, get_required_references, normalize_url},
+    sources::{ReferenceSource, source_for},
+};
+
+/// Options for [`status_references`]
+#[derive(Debug, Clone)]
+pub struct StatusOptions {
+    /// Directory containing the Markdown files with C++ reference links
+    pub contents_dir: PathBuf,
+    /// The languages to report with their cache directories
+    pub caches: Vec<(String, PathBuf)>,
+    /// Whether to report languages whose cache directory does not exist
+    pub include_missing: bool,
+    /// Whether pages are expected to link the cached assets
+    pub assets: bool,
+}
+
+impl StatusOptions {
+    /// Create options using the default directory layout
+    ///
+    /// Every language with a `./cppreference_{lang}` directory is reported.
+    pub fn new() -> Self {
+        Config::default().status_options()
+    }
+}
+
+impl Default for StatusOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How the cache of a language compares with the Markdown files
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStatus {
+    /// The language
+    pub lang: String,
+    /// The cache directory
+    pub cache_dir: PathBuf,
+    /// Number of references in the Markdown files
+    pub required: usize,
+    /// Number of required references that are cached
+    pub present: usize,
+    /// Number of required references that are not cached
+    pub missing: usize,
+    /// Number of cached required pages that are outdated
+    pub stale: usize,
+    /// Number of cached pages that are not required
+    pub orphaned: usize,
+    /// The required references that are not cached
+    pub missing_pages: Vec<String>,
+    /// The cached pages that `ref reprocess --outdated` would regenerate, or
+    /// downloaded from a URL the Markdown files no longer link to
+    pub stale_pages: Vec<String>,
+    /// The cached pages that are not required
+    pub orphaned_pages: Vec<String>,
+}
+
+impl CacheStatus {
+    /// Compare a cache with the required references
+    ///
+    /// The cache directory is only read, not migrated (see [`PageCache::load`]).
+    ///
+    /// # Arguments
+    ///
+    /// * `lang` - The language of the cache
+    /// * `cache_dir` - The cache directory, which may not exist
+    /// * `references` - The required references
+    /// * `source` - The source the pages are downloaded from, to map their URLs back
+    /// * `assets` - Whether pages are expected to link the cached assets
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache directory exists but cannot be read.
+    pub fn new(
+        lang: &str,
+        cache_dir: &Path,
+        references: &HashMap<String, CppReference>,
+        source: &dyn ReferenceSource,
+        assets: bool,
+    ) -> Result<Self, AppError> {
+        let mut status = CacheStatus {
+            lang: lang.to_string(),
+            cache_dir: cache_dir.to_path_buf(),
+            required: references.len(),
+            ..CacheStatus::default()
+        };
+        if !cache_dir.is_dir() {
+            status.missing_pages = references.keys().cloned().collect();
+        } else {
+            let cache = PageCache::load(cache_dir)?;
+            for (name, reference) in references {
+                let Some(entry) = cache.entry(name) else {
+                    status.missing_pages.push(name.clone());
+                    continue;
+                };
+                let metadata = &entry.metadata;
+                // Pages from unknown URLs (e.g. a mirror) are not compared
+                let moved = metadata
+                    .url
+                    .as_deref()
+                    .and_then(|url| source.canonical_url(url))
+                    .is_some_and(|url| normalize_url(&url) != normalize_url(&reference.url));
+                if !metadata.is_current(assets) || moved {
+                    status.stale_pages.push(name.clone());
+                }
+            }
+            status.orphaned_pages = cache
+                .manifest()
+                .pages
+                .keys()
+                .filter(|name| !references.contains_key(*name))
+                .cloned()
+                .collect();
+        }
+
+        for pages in [
+            &mut status.missing_pages,
+            &mut status.stale_pages,
+            &mut status.orphaned_pages,
+        ] {
+            pages.sort_by(|a, b| compare_cpp_names(a, b));
+        }
+        status.missing = status.missing_pages.len();
+        status.present = status.required - status.missing;
+        status.stale = status.stale_pages.len();
+        status.orphaned = status.orphaned_pages.len();
+        Ok(status)
+    }
+}
+
+/// The result of [`status_references`]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StatusReport {
+    /// The status of every reported cache, in the order of the options
+    pub caches: Vec<CacheStatus>,
+}
+
+impl StatusReport {
+    /// Format the report
+    ///
+    /// The text format is a table of the counts followed by the pages that
+    /// need attention.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if JSON serialization fails.
+    pub fn render(&self, format: OutputFormat) -> Result<String, AppError> {
+        match format {
+            OutputFormat::Json => Ok(serde_json::to_string_pretty(self)?),
+            OutputFormat::Text => Ok(self.format_text()),
+        }
+    }
+
+    /// Format the report as a table followed by the pages that need attention
+    fn format_text(&self) -> String {
+        if self.caches.is_empty() {
+            return "No cache directories found; run `ref download` first\n".to_string();
+        }
+
+        let mut output = format!(
+            "{:<8}  {:>8}  {:>7}  {:>7}  {:>5}  {:>8}\n",
+            "Language", "Required", "Present", "Missing", "Stale", "Orphaned"
+        );
+        for status in &self.caches {
+            output.push_str(&format!(
+                "{:<8}  {:>8}  {:>7}  {:>7}  {:>5}  {:>8}\n",
+                status.lang,
+                status.required,
+                status.present,
+                status.missing,
+                status.stale,
+                status.orphaned
+            ));
+        }
+        for status in &self.caches {
+            for (label, pages) in [
+                ("Missing", &status.missing_pages),
+                ("Stale", &status.stale_pages),
+                ("Orphaned", &status.orphaned_pages),
+            ] {
+                if pages.is_empty() {
+                    continue;
+                }
+                output.push_str(&format!("\n{} in {}:\n", label, status.cache_dir.display()));
+                for page in pages {
+                    output.push_str(&format!("  {}\n", page));
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Compare the page caches with the references in the Markdown files
+///
+/// # Arguments
+///
+/// * `options` - Contents directory and the caches to report
+///
+/// # Errors
+///
+/// Returns an error if reference extraction fails, a language has no source,
+/// or a cache directory cannot be read.
+pub fn status_references(options: &StatusOptions) -> Result<StatusReport, AppError> {
+    let references = get_required_references(&options.contents_dir)?;
+    let mut report = StatusReport::default();
+    for (lang, cache_dir) in &options.caches {
+        let source = source_for(lang)?;
+        if !options.include_missing && !cache_dir.is_dir() {
+            continue;
+        }
+        report.caches.push(CacheStatus::new(
+            lang,
+            cache_dir,
+            &references,
+            source,
+            options.assets,
+        )?);
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cache::PageMetadata, commands::download::test_server::write_contents};
+    use std::fs;
+
+    #[test]
+    fn test_status_references() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let contents_dir = write_contents(
+            temp_dir.path(),
+            &[
+                ("std::sort", "cpp/algorithm/sort"),
+                ("std::find", "cpp/algorithm/find"),
+                ("std::count", "cpp/algorithm/count"),
+            ],
+        );
+
+        let zh_dir = temp_dir.path().join("cppreference_zh");
+        fs::create_dir(&zh_dir).unwrap();
+        let mut cache = PageCache::open(&zh_dir).unwrap();
+        for (name, url, version) in [
+            (
+                "std::sort",
+                "https://cppreference.cn/w/cpp/algorithm/sort",
+                2,
+            ),
+            (
+                "std::find",
+                "https://cppreference.cn/w/cpp/algorithm/find_if",
+                2,
+            ),
+            (
+                "std::fill",
+                "https://cppreference.cn/w/cpp/algorithm/fill",
+                1,
+            ),
+        ] {
+            fs::write(cache.page_path(name), "").unwrap();
+            cache.insert(
+                name,
+                PageMetadata {
+                    url: Some(url.to_string()),
+                    processing_version: Some(version),
+                    ..PageMetadata::default()
+                },
+            );
+        }
+        cache.save().unwrap();
+
+        let mut options = StatusOptions {
+            contents_dir,
+            caches: vec![
+                ("en".to_string(), temp_dir.path().join("cppreference_en")),
+                ("zh".to_string(), zh_dir.clone()),
+            ],
+            include_missing: false,
+            assets: false,
+        };
+        let report = status_references(&options).unwrap();
+        assert_eq!(report.caches.len(), 1);
+        let zh = &report.caches[0];
+        assert_eq!(
+            (zh.required, zh.present, zh.missing, zh.stale, zh.orphaned),
+            (3, 2, 1, 1, 1)
+        );
+        assert_eq!(zh.missing_pages, ["std::count"]);
+        assert_eq!(zh.stale_pages, ["std::find"]);
+        assert_eq!(zh.orphaned_pages, ["std::fill"]);
+
+        options.include_missing = true;
+        let report = status_references(&options).unwrap();
+        let en = &report.caches[0];
+        assert_eq!((en.lang.as_str(), en.present, en.missing), ("en", 0, 3));
+        let text = report.render(OutputFormat::Text).unwrap();
+        assert!(text.contains("\nzh               3        2        1      1         1\n"));
+        assert!(text.contains("cppreference_zh:\n  std::fill\n"));
+        let json: serde_json::Value =
+            serde_json::from_str(&report.render(OutputFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["caches"][1]["orphaned_pages"][0], "std::fill");
+
+        // Pages without linked assets are stale once assets are linked
+        options.assets = true;
+        let report = status_references(&options).unwrap();
+        assert_eq!(report.caches[1].stale_pages, ["std::find", "std::sort"]);
+    }
+}
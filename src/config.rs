@@ -0,0 +1,487 @@
+//! Project configuration
+//!
+//! This module loads the optional `algcmp.toml` configuration file. The file is
+//! discovered by searching the current directory and its ancestors, so the tool
+//! can be run from anywhere inside a project. Relative paths in the file are
+//! resolved against the directory containing it.
+//!
+//! # Example
+//!
+//! ```toml
+//! contents_dir = "contents"
+//! cache_dir = "cppreference_{lang}"
+//! lang = "zh"
+//! std = "c++17"
+//! unavailable = "flag"
+//!
+//! [download]
+//! overwrite = false
+//! refresh = false
+//! concurrency = 8
+//! requests_per_second = 4.0
+//! jitter_ms = 100
+//! retries = 5
+//! retry_backoff_ms = 1000
+//! timeout_ms = 60000
+//! redirects = "follow"
+//! assets = true
+//! validate = true
+//! base_url = "http://127.0.0.1:8080/{lang}"
+//!
+//! [lint]
+//! strict = true
+//!
+//! [profiles.zh]
+//! cookies_file = "cookies.txt"
+//! headers = { Accept-Language = "zh-CN,zh-Hans;q=0.9" }
+//!
+//! [print]
+//! colored = true
+//! order = "topic"
+//! output = "out/cppreference_{lang}_print.html"
+//! colored_output = "out/cppreference_{lang}_print_colored.html"
+//! assets = "inline"
+//! ```
+//!
+//! Every key is optional; missing keys fall back to the default layout used
+//! when no configuration file exists. `{lang}` is replaced with the selected
+//! language.
+
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    commands::{
+        download::{DownloadOptions, fetch::RedirectPolicy, profile::RequestProfile},
+        import::ImportOptions,
+        print::{AssetMode, PrintOptions, PrintOrder},
+        prune::PruneOptions,
+        reprocess::ReprocessOptions,
+        status::StatusOptions,
+    },
+    errors::AppError,
+    references::standard::{CppStandard, UnavailablePolicy},
+    sources::languages,
+};
+
+/// Name of the configuration file searched for by [`Config::discover`]
+pub const CONFIG_FILE_NAME: &str = "algcmp.toml";
+
+/// Project configuration loaded from `algcmp.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Directory containing the Markdown files with C++ reference links
+    pub contents_dir: PathBuf,
+    /// Directory the downloaded HTML pages are stored in (`{lang}` is substituted)
+    pub cache_dir: String,
+    /// Default language version
+    pub lang: String,
+    /// C++ standard supported by the judge
+    pub std: Option<CppStandard>,
+    /// What to do with entries unavailable under `std`
+    pub unavailable: UnavailablePolicy,
+    /// Defaults for `ref download`
+    pub download: DownloadConfig,
+    /// Defaults for `ref lint`
+    pub lint: LintConfig,
+    /// Defaults for `ref print`
+    pub print: PrintConfig,
+    /// Request profiles keyed by language
+    pub profiles: BTreeMap<String, RequestProfile>,
+    /// Directory relative paths are resolved against
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+}
+
+/// Defaults for `ref download`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DownloadConfig {
+    /// Overwrite existing files
+    pub overwrite: bool,
+    /// Request cached pages again and rewrite those that changed
+    pub refresh: bool,
+    /// Maximum number of pages downloaded at once
+    pub concurrency: usize,
+    /// Maximum number of requests per second to each host (zero for no limit)
+    pub requests_per_second: f64,
+    /// Upper bound of the random delay added to every request, in milliseconds
+    pub jitter_ms: u64,
+    /// Number of retries of a request failing with a transient error
+    pub retries: u32,
+    /// Delay before the first retry in milliseconds, doubled for every further retry
+    pub retry_backoff_ms: u64,
+    /// Time after which a request is abandoned and retried, in milliseconds
+    pub timeout_ms: u64,
+    /// Which redirects are accepted: "forbid", "same-host" or "follow"
+    pub redirects: RedirectPolicy,
+    /// Download stylesheets, images and fonts and link them locally
+    pub assets: bool,
+    /// Reject challenge pages, pages in another language and truncated pages
+    pub validate: bool,
+    /// URL replacing the origin of the source, e.g. a mirror (`{lang}` is substituted)
+    pub base_url: Option<String>,
+}
+
+/// Defaults for `ref lint`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// Fail on warnings as well as errors
+    pub strict: bool,
+}
+
+/// Defaults for `ref print`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PrintConfig {
+    /// Include colored output
+    pub colored: bool,
+    /// Order of the pages: "name", "topic" or "source"
+    pub order: PrintOrder,
+    /// Output file for flattened output (`{lang}` is substituted)
+    pub output: String,
+    /// Output file for colored output (`{lang}` is substituted)
+    pub colored_output: String,
+    /// How downloaded assets are used: "link" or "inline"
+    pub assets: AssetMode,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            contents_dir: PathBuf::from("contents"),
+            cache_dir: "cppreference_{lang}".to_string(),
+            lang: "en".to_string(),
+            std: None,
+            unavailable: UnavailablePolicy::default(),
+            download: DownloadConfig::default(),
+            lint: LintConfig::default(),
+            print: PrintConfig::default(),
+            profiles: BTreeMap::new(),
+            base_dir: PathBuf::from("."),
+        }
+    }
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        DownloadConfig {
+            overwrite: false,
+            refresh: false,
+            concurrency: 4,
+            requests_per_second: 2.0,
+            jitter_ms: 250,
+            retries: 3,
+            retry_backoff_ms: 500,
+            timeout_ms: 30_000,
+            redirects: RedirectPolicy::default(),
+            assets: true,
+            validate: true,
+            base_url: None,
+        }
+    }
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        PrintConfig {
+            colored: false,
+            order: PrintOrder::default(),
+            output: "cppreference_{lang}_print.html".to_string(),
+            colored_output: "cppreference_{lang}_print_colored.html".to_string(),
+            assets: AssetMode::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load a configuration file
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the configuration file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not valid configuration.
+    pub fn load(path: &Path) -> Result<Config, AppError> {
+        let content = fs::read_to_string(path)?;
+        let mut config: Config = toml::from_str(&content).map_err(|e| AppError::ConfigError {
+            file: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        config.base_dir = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        Ok(config)
+    }
+
+    /// Find the configuration file for a directory
+    ///
+    /// This function searches `start` and each of its ancestors for a file
+    /// named [`CONFIG_FILE_NAME`] and returns the first one found.
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Load the configuration to use for a run
+    ///
+    /// If `explicit` is given, that file is loaded. Otherwise the configuration
+    /// file is discovered from the current directory, falling back to the
+    /// default configuration if there is none.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration file cannot be loaded.
+    pub fn resolve(explicit: Option<&Path>) -> Result<Config, AppError> {
+        if let Some(path) = explicit {
+            return Config::load(path);
+        }
+
+        let current_dir = std::env::current_dir()?;
+        match Config::discover(&current_dir) {
+            Some(path) => {
+                log::debug!("Using configuration file {:?}", path);
+                Config::load(&path)
+            }
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// The contents directory, resolved against the configuration directory
+    pub fn contents_dir(&self) -> PathBuf {
+        self.base_dir.join(&self.contents_dir)
+    }
+
+    /// The cache directory for a language, resolved against the configuration directory
+    pub fn cache_dir(&self, lang: &str) -> PathBuf {
+        self.base_dir.join(substitute_lang(&self.cache_dir, lang))
+    }
+
+    /// The print output file for a language, resolved against the configuration directory
+    pub fn print_output(&self, lang: &str, colored: bool) -> PathBuf {
+        let template = if colored {
+            &self.print.colored_output
+        } else {
+            &self.print.output
+        };
+        self.base_dir.join(substitute_lang(template, lang))
+    }
+
+    /// The request profile for a language, with paths resolved against the configuration directory
+    pub fn profile(&self, lang: &str) -> RequestProfile {
+        let mut profile = self.profiles.get(lang).cloned().unwrap_or_default();
+        if let Some(file) = &profile.cookies_file {
+            profile.cookies_file = Some(self.base_dir.join(file));
+        }
+        profile
+    }
+
+    /// Build [`DownloadOptions`] from this configuration
+    pub fn download_options(&self, lang: &str) -> DownloadOptions {
+        DownloadOptions {
+            contents_dir: self.contents_dir(),
+            output_dir: self.cache_dir(lang),
+            lang: lang.to_string(),
+            overwrite: self.download.overwrite,
+            refresh: self.download.refresh,
+            standard: self.std,
+            unavailable: self.unavailable,
+            concurrency: self.download.concurrency,
+            requests_per_second: self.download.requests_per_second,
+            jitter: Duration::from_millis(self.download.jitter_ms),
+            retries: self.download.retries,
+            retry_backoff: Duration::from_millis(self.download.retry_backoff_ms),
+            timeout: Duration::from_millis(self.download.timeout_ms),
+            redirects: self.download.redirects,
+            fix_urls: false,
+            profile: self.profile(lang),
+            assets: self.download.assets,
+            validate: self.download.validate,
+            base_url: self
+                .download
+                .base_url
+                .as_ref()
+                .map(|url| substitute_lang(url, lang)),
+            cassette: None,
+        }
+    }
+
+    /// Build [`ImportOptions`] from this configuration
+    ///
+    /// Pages are written to the English cache directory, since the offline
+    /// archive holds the English pages.
+    pub fn import_options(&self, archive: &Path) -> ImportOptions {
+        ImportOptions {
+            contents_dir: self.contents_dir(),
+            archive: archive.to_path_buf(),
+            output_dir: self.cache_dir("en"),
+            overwrite: self.download.overwrite,
+        }
+    }
+
+    /// Build [`ReprocessOptions`] from this configuration
+    ///
+    /// Cached assets are linked if assets are downloaded.
+    pub fn reprocess_options(&self, lang: &str) -> ReprocessOptions {
+        ReprocessOptions {
+            cache_dir: self.cache_dir(lang),
+            lang: lang.to_string(),
+            assets: self.download.assets,
+            outdated: false,
+        }
+    }
+
+    /// Build [`StatusOptions`] from this configuration
+    ///
+    /// Every language whose cache directory exists is reported.
+    pub fn status_options(&self) -> StatusOptions {
+        StatusOptions {
+            contents_dir: self.contents_dir(),
+            caches: languages()
+                .map(|lang| (lang.to_string(), self.cache_dir(lang)))
+                .collect(),
+            include_missing: false,
+            assets: self.download.assets,
+        }
+    }
+
+    /// Build [`PruneOptions`] from this configuration
+    pub fn prune_options(&self, lang: &str) -> PruneOptions {
+        PruneOptions {
+            contents_dir: self.contents_dir(),
+            cache_dir: self.cache_dir(lang),
+            archive: None,
+        }
+    }
+
+    /// Build [`PrintOptions`] from this configuration
+    pub fn print_options(&self, lang: &str, colored: bool) -> PrintOptions {
+        PrintOptions {
+            contents_dir: self.contents_dir(),
+            input_dir: self.cache_dir(lang),
+            output_file: self.print_output(lang, colored),
+            lang: lang.to_string(),
+            colored,
+            standard: self.std,
+            unavailable: self.unavailable,
+            order: self.print.order,
+            assets: self.print.assets,
+        }
+    }
+}
+
+/// Replace the `{lang}` placeholder in a path template
+pub fn substitute_lang(template: &str, lang: &str) -> String {
+    template.replace("{lang}", lang)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_resolves_relative_paths() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_file = temp_dir.path().join(CONFIG_FILE_NAME);
+        fs::write(
+            &config_file,
+            r#"contents_dir = "book/contents"
+cache_dir = "cache/{lang}"
+lang = "zh"
+std = "c++17"
+
+[download]
+concurrency = 8
+jitter_ms = 100
+timeout_ms = 5000
+redirects = "forbid"
+validate = false
+base_url = "http://mirror.local/{lang}/"
+
+[print]
+colored = true
+order = "source"
+assets = "inline"
+
+[profiles.zh]
+cookies_file = "cookies.txt"
+
+[profiles.zh.headers]
+Accept-Language = "zh-CN"
+"#,
+        )
+        .unwrap();
+
+        let config = Config::load(&config_file).unwrap();
+        assert_eq!(config.lang, "zh");
+        assert_eq!(config.std, Some(CppStandard::Cpp17));
+        assert_eq!(config.unavailable, UnavailablePolicy::Exclude);
+        assert!(config.print.colored);
+        assert_eq!(config.print.order, PrintOrder::Source);
+        assert_eq!(config.print.assets, AssetMode::Inline);
+        assert!(config.download.assets);
+        assert!(!config.download.validate);
+        assert_eq!(
+            config.download_options("zh").base_url.as_deref(),
+            Some("http://mirror.local/zh/")
+        );
+        assert!(!config.download.overwrite);
+        let options = config.download_options("zh");
+        assert_eq!(options.concurrency, 8);
+        assert_eq!(options.requests_per_second, 2.0);
+        assert_eq!(options.jitter, Duration::from_millis(100));
+        assert_eq!(options.retries, 3);
+        assert_eq!(options.timeout, Duration::from_secs(5));
+        assert_eq!(options.redirects, RedirectPolicy::Forbid);
+        assert_eq!(
+            options.profile.cookies_file,
+            Some(temp_dir.path().join("cookies.txt"))
+        );
+        assert_eq!(options.profile.headers["Accept-Language"], "zh-CN");
+        assert_eq!(config.profile("en"), RequestProfile::default());
+        assert_eq!(config.contents_dir(), temp_dir.path().join("book/contents"));
+        assert_eq!(config.cache_dir("zh"), temp_dir.path().join("cache/zh"));
+        assert_eq!(
+            config.print_output("zh", true),
+            temp_dir.path().join("cppreference_zh_print_colored.html")
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_keys() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config_file = temp_dir.path().join(CONFIG_FILE_NAME);
+        fs::write(&config_file, "contents = \"contents\"\n").unwrap();
+
+        match Config::load(&config_file).unwrap_err() {
+            AppError::ConfigError { file, .. } => assert!(file.ends_with(CONFIG_FILE_NAME)),
+            other => panic!("Expected ConfigError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_discover_searches_ancestors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let nested = temp_dir.path().join("a/b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(temp_dir.path().join(CONFIG_FILE_NAME), "").unwrap();
+
+        assert_eq!(
+            Config::discover(&nested),
+            Some(temp_dir.path().join(CONFIG_FILE_NAME))
+        );
+    }
+}
@@ -5,6 +5,8 @@
 
 use thiserror::Error;
 
+use crate::{cache::file_name_for, references::NameConflict};
+
 /// Application error types
 ///
 /// This enum represents all possible errors that can occur during the execution
@@ -14,39 +16,85 @@ pub enum AppError {
     /// IO error
     #[error("IO error: {0}")]
     IoError(#[from] std::io::Error),
-    /// Regex error
-    #[error("Regex error: {0}")]
-    RegexError(#[from] regex::Error),
+    /// JSON error
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    /// Zip archive error
+    #[error("Zip error: {0}")]
+    ZipError(#[from] zip::result::ZipError),
     /// HTTP error
     #[error("HTTP error: {0}")]
     HttpError(#[from] reqwest::Error),
+    /// Unsuccessful HTTP response status
+    #[error("HTTP status {} for {url}", status_text(*.status))]
+    HttpStatus { url: String, status: u16 },
+    /// A request was redirected against the redirect policy
+    #[error("URL {url} redirected to {final_url}")]
+    Redirected { url: String, final_url: String },
+    /// A base URL to download pages from is not an HTTP(S) URL
+    #[error("Invalid base URL '{url}', expected an HTTP(S) URL such as http://127.0.0.1:8080")]
+    InvalidBaseUrl { url: String },
+    /// A request rate too small for the time between requests to be represented
+    #[error("Invalid request rate {rate} per second, the time between requests is too long")]
+    InvalidRequestRate { rate: f64 },
+    /// A replayed cassette has no exchange with a URL
+    #[error("No recorded response for {url} in {dir}")]
+    NotRecorded { url: String, dir: String },
+    /// A cassette file cannot be read
+    #[error("Invalid cassette file {file}: {reason}")]
+    InvalidCassette { file: String, reason: String },
+    /// A downloaded page is not a complete reference page
+    #[error("Invalid page {url}: {reason}")]
+    InvalidPage { url: String, reason: String },
+    /// Some pages could not be downloaded
+    #[error("Failed to download {failed} of {total} page(s)")]
+    DownloadFailed { failed: usize, total: usize },
     /// Missing URL in a Markdown file
-    #[error("Missing URL in {file}:{line}")]
-    MissingUrl { file: String, line: usize },
+    #[error("Missing URL in {file}:{line}:{column}")]
+    MissingUrl {
+        file: String,
+        line: usize,
+        column: usize,
+    },
     /// Invalid file format in a Markdown file
-    #[error("Invalid file format in {file}:{line}")]
-    InvalidFileFormat { file: String, line: usize },
-    /// Duplicate entry with conflicting information
-    #[error("Duplicate entry with conflicting information: {name} at {url1} and {url2}")]
-    DuplicateConflict {
-        name: String,
-        url1: String,
-        url2: String,
+    #[error("Invalid file format in {file}:{line}:{column}")]
+    InvalidFileFormat {
+        file: String,
+        line: usize,
+        column: usize,
+    },
+    /// Names or aliases linked to different pages
+    #[error(
+        "{count} name(s) linked to conflicting URLs: {}",
+        .conflicts.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+    )]
+    DuplicateConflicts {
+        count: usize,
+        conflicts: Vec<NameConflict>,
     },
     /// Missing required HTML files
     #[error("Missing {count} required HTML file(s): {files}")]
     MissingRequiredFiles { count: usize, files: String },
+    /// No reference source for a language
+    #[error("Unknown language '{lang}', expected one of: {known}")]
+    UnknownLanguage { lang: String, known: String },
+    /// Invalid configuration file
+    #[error("Invalid configuration in {file}: {reason}")]
+    ConfigError { file: String, reason: String },
+    /// The contents failed the lint
+    #[error("Lint failed with {errors} error(s) and {warnings} warning(s)")]
+    LintFailed { errors: usize, warnings: usize },
     /// HTML parsing error
     #[error("HTML parsing error in {file}: {reason}")]
     HtmlParsingError { file: String, reason: String },
 }
 
 impl AppError {
-    /// Create a new MissingRequiredFiles error
+    /// Create a new MissingRequiredFiles error from the names of the missing pages
     pub fn missing_files(files: &[String]) -> Self {
         let files_str = files
             .iter()
-            .map(|f| format!("{}.html", f))
+            .map(|f| format!("{} ({})", f, file_name_for(f)))
             .collect::<Vec<_>>()
             .join(", ");
         AppError::MissingRequiredFiles {
@@ -55,3 +103,14 @@ impl AppError {
         }
     }
 }
+
+/// Format an HTTP status code with its reason phrase (e.g. `404 Not Found`)
+fn status_text(status: u16) -> String {
+    match reqwest::StatusCode::from_u16(status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+    {
+        Some(reason) => format!("{} {}", status, reason),
+        None => status.to_string(),
+    }
+}
@@ -3,9 +3,14 @@
 //! This module provides functionality for processing HTML documents:
 //!
 //! - Removing navigation elements from cppreference pages
+//! - Finding and rewriting the links to stylesheets, images and fonts
 //! - Flattening code blocks for non-colored printing
 //! - Concatenating multiple HTML documents
 
+mod assets;
 mod processing;
 
-pub use processing::{flatten_code_blocks, remove_navigation_elements};
+pub use assets::{
+    asset_extension, asset_urls, css_urls, data_url, rewrite_asset_urls, rewrite_css_urls,
+};
+pub use processing::{PROCESSING_VERSION, flatten_code_blocks, remove_navigation_elements};
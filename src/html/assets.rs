@@ -0,0 +1,300 @@
+//! Asset links in HTML and CSS
+//!
+//! Pages refer to stylesheets (`<link rel="stylesheet">`) and images
+//! (`<img>`), and stylesheets refer to fonts, images and other stylesheets
+//! (`url(...)` and `@import`). This module finds these references and rewrites
+//! them, so that they can point to local copies instead.
+
+use base64::Engine;
+use scraper::{Html, Selector, node::Node};
+use std::ops::Range;
+
+/// File extensions and MIME types of the assets that are stored
+const ASSET_TYPES: [(&str, &str); 13] = [
+    ("css", "text/css"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+    ("ico", "image/x-icon"),
+    ("woff2", "font/woff2"),
+    ("woff", "font/woff"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("eot", "application/vnd.ms-fontobject"),
+];
+
+/// Elements referring to assets, with the attribute holding the URL
+const ASSET_ELEMENTS: [(&str, &str); 2] = [
+    (r#"link[rel~="stylesheet"][href]"#, "href"),
+    ("img[src]", "src"),
+];
+
+/// The asset URLs of an HTML document, in document order and without duplicates
+///
+/// These are the `href` attributes of stylesheet links and the `src`
+/// attributes of images, as written in the document (possibly relative).
+pub fn asset_urls(content: &str) -> Vec<String> {
+    let html = Html::parse_document(content);
+    let mut urls = Vec::new();
+    for (selector, attribute) in ASSET_ELEMENTS {
+        let selector = Selector::parse(selector).unwrap();
+        for element in html.select(&selector) {
+            if let Some(url) = element.value().attr(attribute)
+                && is_remote(url)
+                && !urls.iter().any(|known| known == url)
+            {
+                urls.push(url.to_string());
+            }
+        }
+    }
+    urls
+}
+
+/// Rewrite the asset URLs of an HTML document
+///
+/// Images whose URL is rewritten lose their `srcset` attribute, so that
+/// browsers do not prefer the remote variants.
+///
+/// # Arguments
+///
+/// * `content` - The HTML content
+/// * `rewrite` - Maps a URL as written in the document to its replacement, or
+///   `None` to keep it
+///
+/// # Returns
+///
+/// The HTML content with the URLs replaced, or the original content if no URL
+/// was replaced.
+pub fn rewrite_asset_urls(
+    content: &str,
+    mut rewrite: impl FnMut(&str) -> Option<String>,
+) -> String {
+    let mut html = Html::parse_document(content);
+    let mut targets = Vec::new();
+    for (selector, attribute) in ASSET_ELEMENTS {
+        let selector = Selector::parse(selector).unwrap();
+        targets.extend(
+            html.select(&selector)
+                .map(|element| (element.id(), attribute)),
+        );
+    }
+
+    let mut changed = false;
+    for (id, attribute) in targets {
+        let Some(mut node) = html.tree.get_mut(id) else {
+            continue;
+        };
+        let Node::Element(element) = node.value() else {
+            continue;
+        };
+        let Some(index) = element
+            .attrs
+            .iter()
+            .position(|(name, _)| &*name.local == attribute)
+        else {
+            continue;
+        };
+        if let Some(url) = rewrite(&element.attrs[index].1) {
+            element.attrs[index].1 = url.as_str().into();
+            element.attrs.retain(|(name, _)| &*name.local != "srcset");
+            changed = true;
+        }
+    }
+
+    if changed {
+        html.html()
+    } else {
+        content.to_string()
+    }
+}
+
+/// The URLs referred to by a stylesheet, in order and without duplicates
+///
+/// These are the arguments of `url(...)` and the strings after `@import`.
+/// `data:` URLs and fragments are left out.
+pub fn css_urls(css: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for (_, url) in css_url_spans(css) {
+        if !urls.contains(&url) {
+            urls.push(url);
+        }
+    }
+    urls
+}
+
+/// Rewrite the URLs of a stylesheet
+///
+/// # Arguments
+///
+/// * `css` - The stylesheet
+/// * `rewrite` - Maps a URL as written in the stylesheet to its replacement,
+///   or `None` to keep it
+pub fn rewrite_css_urls(css: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut end = 0;
+    for (span, url) in css_url_spans(css) {
+        if let Some(replacement) = rewrite(&url) {
+            output.push_str(&css[end..span.start]);
+            output.push_str(&replacement);
+            end = span.end;
+        }
+    }
+    output.push_str(&css[end..]);
+    output
+}
+
+/// The file extension an asset is stored with
+///
+/// The extension is taken from the `Content-Type` of the response, since
+/// stylesheets are often served by scripts (e.g. `load.php?...`), and
+/// otherwise from the URL. Unknown types are stored as `.bin`.
+///
+/// # Examples
+///
+/// ```
+/// use algcmp::html::asset_extension;
+///
+/// let url = "https://en.cppreference.com/mwiki/load.php?modules=site&only=styles";
+/// assert_eq!(asset_extension(Some("text/css; charset=utf-8"), url), "css");
+/// assert_eq!(asset_extension(None, "https://example.com/logo.PNG?v=2"), "png");
+/// ```
+pub fn asset_extension(content_type: Option<&str>, url: &str) -> &'static str {
+    let mime = content_type
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|mime| mime.trim().to_ascii_lowercase());
+    if let Some((extension, _)) = ASSET_TYPES
+        .iter()
+        .find(|(_, known)| mime.as_deref() == Some(*known))
+    {
+        return extension;
+    }
+
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    ASSET_TYPES
+        .iter()
+        .find(|(known, _)| extension.as_deref() == Some(*known))
+        .map_or("bin", |(extension, _)| extension)
+}
+
+/// A `data:` URL embedding a stored asset
+///
+/// # Arguments
+///
+/// * `file_name` - The file name of the asset, whose extension selects the MIME type
+/// * `content` - The content of the asset
+pub fn data_url(file_name: &str, content: &[u8]) -> String {
+    let extension = file_name.rsplit_once('.').map(|(_, extension)| extension);
+    let mime = ASSET_TYPES
+        .iter()
+        .find(|(known, _)| extension == Some(*known))
+        .map_or("application/octet-stream", |(_, mime)| mime);
+    format!(
+        "data:{};base64,{}",
+        mime,
+        base64::engine::general_purpose::STANDARD.encode(content)
+    )
+}
+
+/// Whether a URL refers to another resource, rather than being embedded or a fragment
+fn is_remote(url: &str) -> bool {
+    let url = url.trim();
+    !url.is_empty() && !url.starts_with('#') && !url.starts_with("data:")
+}
+
+/// The URLs of a stylesheet with the byte ranges they occupy, in order
+fn css_url_spans(css: &str) -> Vec<(Range<usize>, String)> {
+    let mut spans = Vec::new();
+    for (keyword, quoted_only) in [("url(", false), ("@import", true)] {
+        let mut offset = 0;
+        while let Some(index) = css[offset..].find(keyword) {
+            let start = offset + index + keyword.len();
+            offset = start;
+            let rest = &css[start..];
+            let skipped = rest.len() - rest.trim_start().len();
+            let value_start = start + skipped;
+            let span = match css[value_start..].chars().next() {
+                Some(quote @ ('"' | '\'')) => css[value_start + 1..]
+                    .find(quote)
+                    .map(|end| value_start + 1..value_start + 1 + end),
+                Some(_) if !quoted_only => css[value_start..].find(')').map(|end| {
+                    value_start..value_start + css[value_start..value_start + end].trim_end().len()
+                }),
+                _ => None,
+            };
+            if let Some(span) = span
+                && is_remote(&css[span.clone()])
+            {
+                let url = css[span.clone()].to_string();
+                offset = span.end;
+                spans.push((span, url));
+            }
+        }
+    }
+    spans.sort_by_key(|(span, _)| span.start);
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_asset_urls() {
+        let html = r#"<!DOCTYPE html><html><head><link href="/site.css?x=1&amp;y=2" rel="stylesheet"><link href="/favicon.ico" rel="shortcut icon"></head><body><img src="//example.com/a.png" srcset="//example.com/a@2x.png 2x"><img src="data:image/png;base64,AA=="></body></html>"#;
+        assert_eq!(
+            asset_urls(html),
+            ["/site.css?x=1&y=2", "//example.com/a.png"]
+        );
+
+        let rewritten = rewrite_asset_urls(html, |url| match url {
+            "/site.css?x=1&y=2" => Some("assets/1.css".to_string()),
+            "//example.com/a.png" => Some("assets/2.png".to_string()),
+            _ => None,
+        });
+        assert!(rewritten.contains(r#"<link href="assets/1.css" rel="stylesheet">"#));
+        assert!(rewritten.contains(r#"<img src="assets/2.png">"#));
+        assert!(rewritten.contains(r#"href="/favicon.ico""#));
+        assert_eq!(rewrite_asset_urls(html, |_| None), html);
+    }
+
+    #[test]
+    fn test_rewrite_css_urls() {
+        let css = "@import \"print.css\";\n\
+                   @font-face { src: url( 'fonts/a.woff2' ) format('woff2'), url(fonts/a.woff); }\n\
+                   .logo { background: url(\"data:image/png;base64,AA==\"), url(#mask); }\n\
+                   .icon { background: url(/img/icon.png) }";
+        assert_eq!(
+            css_urls(css),
+            [
+                "print.css",
+                "fonts/a.woff2",
+                "fonts/a.woff",
+                "/img/icon.png"
+            ]
+        );
+
+        let rewritten = rewrite_css_urls(css, |url| {
+            (url != "print.css").then(|| format!("local-{}", url.replace('/', "-")))
+        });
+        assert!(rewritten.starts_with("@import \"print.css\";"));
+        assert!(rewritten.contains("url( 'local-fonts-a.woff2' )"));
+        assert!(rewritten.contains("url(local-fonts-a.woff)"));
+        assert!(rewritten.contains("url(local--img-icon.png)"));
+        assert!(rewritten.contains("url(#mask)"));
+    }
+
+    #[test]
+    fn test_data_url() {
+        assert_eq!(data_url("0123.css", b"a{}"), "data:text/css;base64,YXt9");
+        assert_eq!(
+            data_url("0123.bin", &[0xff]),
+            "data:application/octet-stream;base64,/w=="
+        );
+    }
+}
@@ -12,62 +12,75 @@ use scraper::{Html, HtmlTreeSink, Selector};
 
 use crate::errors::AppError;
 
+/// Version of the processing applied to downloaded pages
+///
+/// It is recorded for every downloaded page and must be increased whenever the
+/// processing changes, so that `ref download --refresh` reprocesses pages
+/// instead of keeping them because they are unchanged upstream. Whether the
+/// links to stylesheets and images were rewritten is recorded separately, as
+/// [`PageMetadata::assets_linked`].
+///
+/// - 1: navigation elements removed by [`remove_navigation_elements`]
+/// - 2: links to stylesheets and images rewritten to local copies
+///
+/// [`PageMetadata::assets_linked`]: crate::cache::PageMetadata::assets_linked
+pub const PROCESSING_VERSION: u32 = 2;
+
 /// Remove navigation elements from HTML content
 ///
-/// This function removes elements with class `t-navbar` and id `mw-head` from
-/// the HTML content. These are navigation elements from cppreference.com that
-/// are not needed for printing.
+/// This function removes the first element matching each selector (for
+/// cppreference.com, the element with class `t-navbar` and the one with id
+/// `mw-head`, see [`NAVIGATION_SELECTORS`]). These are navigation elements
+/// that are not needed for printing.
 ///
-/// If either element is not found exactly once, a warning is logged and the
-/// original content is returned unchanged.
+/// If any selector matches no element, a warning is logged and the original
+/// content is returned unchanged.
 ///
 /// # Arguments
 ///
 /// * `content` - The HTML content as a string
 /// * `name` - The name of the C++ reference (for logging purposes)
+/// * `selectors` - The CSS selectors of the elements to remove
 ///
 /// # Returns
 ///
 /// The processed HTML content with navigation elements removed, or the original
-/// content if processing fails.
-pub fn remove_navigation_elements(content: &str, name: &str) -> Result<String, AppError> {
+/// content if an element is missing.
+///
+/// # Errors
+///
+/// Returns [`AppError::HtmlParsingError`] if a selector is invalid.
+///
+/// [`NAVIGATION_SELECTORS`]: crate::sources::NAVIGATION_SELECTORS
+pub fn remove_navigation_elements(
+    content: &str,
+    name: &str,
+    selectors: &[&str],
+) -> Result<String, AppError> {
     let html = Html::parse_document(content);
     let tree_sink = HtmlTreeSink::new(html);
 
-    // Remove t-navbar div
-    let navbar_selector = Selector::parse(".t-navbar").unwrap();
-    let navbar_found = {
-        let html_ref = tree_sink.0.borrow();
-        if let Some(elem) = html_ref.select(&navbar_selector).next() {
-            let id = elem.id();
-            drop(html_ref);
-            tree_sink.remove_from_parent(&id);
-            true
-        } else {
-            false
-        }
-    };
-
-    // Remove mw-head element
-    let head_selector = Selector::parse("#mw-head").unwrap();
-    let head_found = {
+    let mut missing = Vec::new();
+    for &selector in selectors {
+        let parsed = Selector::parse(selector).map_err(|e| AppError::HtmlParsingError {
+            file: name.to_string(),
+            reason: format!("invalid selector {:?}: {}", selector, e),
+        })?;
         let html_ref = tree_sink.0.borrow();
-        if let Some(elem) = html_ref.select(&head_selector).next() {
+        if let Some(elem) = html_ref.select(&parsed).next() {
             let id = elem.id();
             drop(html_ref);
             tree_sink.remove_from_parent(&id);
-            true
         } else {
-            false
+            missing.push(selector);
         }
-    };
+    }
 
-    if !navbar_found || !head_found {
+    if !missing.is_empty() {
         warn!(
-            "Unexpected element count for {}: t-navbar={}, mw-head={}. Skipping element removal.",
-            name,
-            if navbar_found { 1 } else { 0 },
-            if head_found { 1 } else { 0 }
+            "Navigation element(s) {} not found for {}. Skipping element removal.",
+            missing.join(", "),
+            name
         );
         return Ok(content.to_string());
     }
@@ -132,6 +145,22 @@ pub fn flatten_code_blocks(content: &str) -> Result<String, AppError> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_remove_navigation_elements() {
+        let html = r#"<!DOCTYPE html><html><body><div id="mw-head">head</div><div class="t-navbar">nav</div><p>text</p></body></html>"#;
+        let selectors = [".t-navbar", "#mw-head"];
+        let result = remove_navigation_elements(html, "std::sort", &selectors).unwrap();
+        assert!(!result.contains("nav") && !result.contains("head</div>"));
+        assert!(result.contains("<p>text</p>"));
+
+        let result = remove_navigation_elements(html, "std::sort", &[".t-navbar", "#p-nav"]);
+        assert_eq!(result.unwrap(), html);
+        assert!(matches!(
+            remove_navigation_elements(html, "std::sort", &["##"]),
+            Err(AppError::HtmlParsingError { .. })
+        ));
+    }
+
     #[test]
     fn test_flatten_code_blocks_basic() {
         let html =
@@ -0,0 +1,40 @@
+#![forbid(unsafe_code)]
+
+//! C++ Reference Manager for Algorithm Competition
+//!
+//! This crate implements the pipeline behind the `algcmp` binary so that it can
+//! also be embedded in other tools:
+//!
+//! 1. [`references`] extracts C++ reference links from Markdown files
+//! 2. [`commands::download`] downloads the referenced cppreference.com pages
+//!    from one of the [`sources`] into a [`cache`] directory
+//! 3. [`html`] cleans up the downloaded pages
+//! 4. [`commands::print`] concatenates the pages into a single printable file
+//!
+//! # Example
+//!
+//! ```no_run
+//! use algcmp::commands::{
+//!     download::{DownloadOptions, download_references},
+//!     print::{PrintOptions, print_references},
+//! };
+//!
+//! # async fn run() -> Result<(), algcmp::errors::AppError> {
+//! let mut download_options = DownloadOptions::new("en");
+//! download_options.contents_dir = "./my_contents".into();
+//! download_references(&download_options).await?;
+//!
+//! let print_options = PrintOptions::new("en", true);
+//! print_references(&print_options)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cache;
+pub mod commands;
+pub mod config;
+pub mod errors;
+pub mod html;
+pub mod references;
+pub mod sources;
+pub mod utils;
@@ -10,13 +10,37 @@
 //! ## `ref download`
 //! Extracts C++ reference URLs from Markdown files in `./contents`, downloads the corresponding
 //! HTML pages from cppreference.com, and processes them by removing navigation elements.
-//! Only downloads missing files unless `--overwrite` is specified.
+//! Only downloads missing files unless `--overwrite` is specified; `--refresh` sends conditional
+//! requests for cached pages and only rewrites those that changed upstream. Pages are downloaded
+//! concurrently within a per-host requests-per-second budget. Transient failures are retried,
+//! and a summary of downloaded, skipped and failed pages is printed at the end.
+//!
+//! ## `ref import`
+//! Fills `./cppreference_en` from the offline HTML book published by cppreference (a zip file
+//! or the directory it was extracted to), so that pages can be printed without network access.
+//!
+//! ## `ref reprocess`
+//! Regenerates the pages in `./cppreference_{lang}` from the pages as served, which are kept in
+//! its `raw` subdirectory, so that changes to the processing apply without downloading again.
 //!
 //! ## `ref print`
-//! Concatenates all downloaded HTML files in `./cppreference` into a single file for printing.
+//! Concatenates all downloaded HTML files in `./cppreference_{lang}` into a single file for printing.
 //! Supports colored output (preserving syntax highlighting) or flattened output (removing
 //! syntax highlighting for non-colored printing).
 //!
+//! ## `ref status`
+//! Counts, for every language with a cache directory, the pages required by `./contents` that are
+//! present, missing or stale (processed by an older version or downloaded from a URL that
+//! changed), and the cached pages that are orphaned because no Markdown file refers to them.
+//!
+//! ## `ref prune`
+//! Lists the orphaned pages of `./cppreference_{lang}` and, after confirmation, deletes them or
+//! moves them to an archive directory.
+//!
+//! ## `ref lint`
+//! Checks the Markdown files in `./contents` for malformed tables, links that would not be
+//! downloaded and inconsistent headers, and exits with an error if problems are found.
+//!
 //! # Usage
 //!
 //! ```bash
@@ -32,30 +56,75 @@
 //! # Directory Structure
 //!
 //! - `./contents/` - Markdown files containing C++ reference links
-//! - `./cppreference/` - Downloaded HTML files from cppreference.com
-//! - `./cppreference_print.html` - Generated printable HTML (flattened)
-//! - `./cppreference_print_colored.html` - Generated printable HTML (colored)
-
-use clap::{Parser, Subcommand};
-
-// Import modules
-mod commands;
-mod errors;
-mod html;
-mod references;
-mod utils;
+//! - `./cppreference_{lang}/` - Downloaded HTML files from cppreference.com
+//! - `./cppreference_{lang}_print.html` - Generated printable HTML (flattened)
+//! - `./cppreference_{lang}_print_colored.html` - Generated printable HTML (colored)
+//!
+//! All of these locations, the default language and per-command defaults can be
+//! changed in an `algcmp.toml` file, which is searched for in the current
+//! directory and its ancestors (or given with `--config`).
+//!
+//! The same pipeline is available as a library; see the `algcmp` crate documentation.
 
-use crate::commands::{download::download_references, print::print_references};
-use crate::errors::AppError;
+use algcmp::{
+    commands::{
+        OutputFormat,
+        download::{cassette::CassetteMode, download_references, fetch::RedirectPolicy},
+        import::import_references,
+        lint::{LintOptions, lint_references},
+        list::{ListOptions, list_references},
+        print::{AssetMode, PrintOrder, print_references},
+        prune::{orphaned_pages, prune_references},
+        reprocess::reprocess_references,
+        status::status_references,
+    },
+    config::{Config, substitute_lang},
+    errors::AppError,
+    references::standard::{CppStandard, UnavailablePolicy},
+    sources::source_for,
+};
+use clap::{Args, Parser, Subcommand};
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "cppreference-downloader")]
 #[command(about = "Download C++ references from cppreference.com")]
 struct Cli {
+    #[command(flatten)]
+    paths: PathArgs,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// Options overriding the configuration file
+#[derive(Args, Debug)]
+struct PathArgs {
+    /// Configuration file (default: `algcmp.toml` in the current directory or an ancestor)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Directory containing the Markdown files
+    #[arg(long, global = true)]
+    contents_dir: Option<PathBuf>,
+    /// Directory for downloaded HTML files (`{lang}` is replaced with the language)
+    #[arg(long, global = true)]
+    cache_dir: Option<String>,
+}
+
+/// Options selecting the C++ standard supported by the judge
+#[derive(Args, Debug)]
+struct StandardArgs {
+    /// C++ standard supported by the judge (e.g. c++17, c++20)
+    #[arg(long = "std")]
+    standard: Option<CppStandard>,
+    /// What to do with entries unavailable under `--std`: "exclude" or "flag"
+    #[arg(long)]
+    unavailable: Option<UnavailablePolicy>,
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     Ref {
@@ -68,19 +137,136 @@ enum Commands {
 enum RefSubcommands {
     Download {
         /// Overwrite existing files
-        #[arg(long, default_value_t = false)]
-        overwrite: bool,
-        /// Language version: "en" for English (default), "zh" for Chinese
-        #[arg(long, default_value = "en")]
-        lang: String,
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        overwrite: Option<bool>,
+        /// Request cached pages again and only rewrite those that changed upstream
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        refresh: Option<bool>,
+        /// Language version: en (default), de, es, fr, it, ja, pt, ru or zh (cppreference.cn)
+        #[arg(long)]
+        lang: Option<String>,
+        /// Maximum number of pages downloaded at once (default: 4)
+        #[arg(long)]
+        concurrency: Option<usize>,
+        /// Maximum number of requests per second to each host, 0 for no limit (default: 2)
+        #[arg(long)]
+        requests_per_second: Option<f64>,
+        /// Upper bound of the random delay added to every request (default: 250)
+        #[arg(long)]
+        jitter_ms: Option<u64>,
+        /// Number of retries of a request failing with a transient error (default: 3)
+        #[arg(long)]
+        retries: Option<u32>,
+        /// Delay before the first retry, doubled for every further retry (default: 500)
+        #[arg(long)]
+        retry_backoff_ms: Option<u64>,
+        /// Time after which a request is abandoned and retried (default: 30000)
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+        /// Which redirects are accepted: "forbid", "same-host" (default) or "follow"
+        #[arg(long)]
+        redirects: Option<RedirectPolicy>,
+        /// Rewrite the URLs of redirected pages in the Markdown files
+        #[arg(long)]
+        fix_urls: bool,
+        /// Netscape cookies.txt file to send cookies from (overrides the language profile)
+        #[arg(long)]
+        cookies: Option<PathBuf>,
+        /// Download stylesheets, images and fonts and link them locally (default: true)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        assets: Option<bool>,
+        /// Reject challenge pages, pages in another language and truncated pages,
+        /// keeping them in the quarantine directory (default: true)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        validate: Option<bool>,
+        /// Download from this URL instead of the site of the language, e.g. a
+        /// mirror (pages keep their paths)
+        #[arg(long)]
+        base_url: Option<String>,
+        /// Save every HTTP exchange in this directory, to replay the download later
+        #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+        record: Option<PathBuf>,
+        /// Answer requests from the exchanges saved with --record instead of the network
+        #[arg(long, value_name = "DIR")]
+        replay: Option<PathBuf>,
+        #[command(flatten)]
+        standard: StandardArgs,
+    },
+    Import {
+        /// The offline archive: the zip file or the directory it was extracted to
+        #[arg(long)]
+        archive: PathBuf,
+        /// Overwrite pages that are already cached
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        overwrite: Option<bool>,
+    },
+    /// Regenerate the cached pages from the pages as served, without downloading them
+    Reprocess {
+        /// Language version: en (default), de, es, fr, it, ja, pt, ru or zh (cppreference.cn)
+        #[arg(long)]
+        lang: Option<String>,
+        /// Link the cached stylesheets, images and fonts (default: true)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        assets: Option<bool>,
+        /// Only reprocess pages produced by an older version of the processing
+        #[arg(long)]
+        outdated: bool,
     },
     Print {
         /// Include colored output
-        #[arg(long, default_value_t = false)]
-        colored: bool,
-        /// Language version: "en" for English (default), "zh" for Chinese
-        #[arg(long, default_value = "en")]
-        lang: String,
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        colored: Option<bool>,
+        /// Language version: en (default), de, es, fr, it, ja, pt, ru or zh (cppreference.cn)
+        #[arg(long)]
+        lang: Option<String>,
+        /// Output file (`{lang}` is replaced with the language)
+        #[arg(long)]
+        output: Option<String>,
+        /// Order of the pages: "name" (default), "topic" or "source"
+        #[arg(long)]
+        order: Option<PrintOrder>,
+        /// How downloaded assets are used: "link" (default) or "inline"
+        #[arg(long)]
+        assets: Option<AssetMode>,
+        #[command(flatten)]
+        standard: StandardArgs,
+    },
+    /// Count the required, missing, stale and orphaned pages of every cache
+    Status {
+        /// Only report this language, even if its cache directory does not exist
+        #[arg(long)]
+        lang: Option<String>,
+        /// Output format: "text" (default) or "json"
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+        /// Shorthand for --format json
+        #[arg(long, conflicts_with = "format")]
+        json: bool,
+    },
+    /// Delete or archive cached pages no longer referenced by the Markdown files
+    Prune {
+        /// Language version: en (default), de, es, fr, it, ja, pt, ru or zh (cppreference.cn)
+        #[arg(long)]
+        lang: Option<String>,
+        /// Move the pages to this directory instead of deleting them
+        #[arg(long, value_name = "DIR")]
+        archive: Option<PathBuf>,
+        /// Prune without asking for confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    List {
+        /// Output format: "text" (default) or "json"
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+    },
+    Lint {
+        /// Output format: "text" (default) or "json"
+        #[arg(long, default_value = "text")]
+        format: OutputFormat,
+        /// Fail on warnings as well as errors
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        strict: Option<bool>,
     },
 }
 
@@ -88,7 +274,8 @@ enum RefSubcommands {
  * Main function
  *
  * This function parses command-line arguments, initializes the logger,
- * and runs the appropriate command based on user input.
+ * loads the configuration file and runs the appropriate command based on
+ * user input. Command-line options take precedence over the configuration.
  *
  * @return Result indicating success or error
  */
@@ -98,17 +285,239 @@ fn main() -> Result<(), AppError> {
     // Initialize logger
     env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
 
+    let config = Config::resolve(cli.paths.config.as_deref())?;
+    let contents_dir = cli.paths.contents_dir.as_ref();
+    let cache_dir = |lang: &str| {
+        cli.paths
+            .cache_dir
+            .as_ref()
+            .map(|template| PathBuf::from(substitute_lang(template, lang)))
+    };
+
     match &cli.command {
         Commands::Ref { subcommand } => match subcommand {
-            RefSubcommands::Download { overwrite, lang } => {
-                let rt = tokio::runtime::Builder::new_current_thread()
+            RefSubcommands::Download {
+                overwrite,
+                refresh,
+                lang,
+                concurrency,
+                requests_per_second,
+                jitter_ms,
+                retries,
+                retry_backoff_ms,
+                timeout_ms,
+                redirects,
+                fix_urls,
+                cookies,
+                assets,
+                validate,
+                base_url,
+                record,
+                replay,
+                standard,
+            } => {
+                let lang = lang.as_deref().unwrap_or(&config.lang);
+                let mut options = config.download_options(lang);
+                if let Some(dir) = contents_dir {
+                    options.contents_dir = dir.clone();
+                }
+                if let Some(dir) = cache_dir(lang) {
+                    options.output_dir = dir;
+                }
+                if let Some(overwrite) = overwrite {
+                    options.overwrite = *overwrite;
+                }
+                options.refresh = refresh.unwrap_or(options.refresh);
+                options.standard = standard.standard.or(options.standard);
+                options.unavailable = standard.unavailable.unwrap_or(options.unavailable);
+                options.concurrency = concurrency.unwrap_or(options.concurrency);
+                options.requests_per_second =
+                    requests_per_second.unwrap_or(options.requests_per_second);
+                if let Some(jitter_ms) = jitter_ms {
+                    options.jitter = Duration::from_millis(*jitter_ms);
+                }
+                options.retries = retries.unwrap_or(options.retries);
+                if let Some(retry_backoff_ms) = retry_backoff_ms {
+                    options.retry_backoff = Duration::from_millis(*retry_backoff_ms);
+                }
+                if let Some(timeout_ms) = timeout_ms {
+                    options.timeout = Duration::from_millis(*timeout_ms);
+                }
+                options.redirects = redirects.unwrap_or(options.redirects);
+                options.fix_urls = *fix_urls;
+                if let Some(cookies) = cookies {
+                    options.profile.cookies_file = Some(cookies.clone());
+                }
+                options.assets = assets.unwrap_or(options.assets);
+                options.validate = validate.unwrap_or(options.validate);
+                if let Some(base_url) = base_url {
+                    options.base_url = Some(base_url.clone());
+                }
+                if let Some(dir) = record {
+                    options.cassette = Some(CassetteMode::Record(dir.clone()));
+                }
+                if let Some(dir) = replay {
+                    options.cassette = Some(CassetteMode::Replay(dir.clone()));
+                }
+
+                let rt = tokio::runtime::Builder::new_multi_thread()
                     .enable_all()
-                    .build()
-                    .unwrap();
+                    .build()?;
+
+                let summary = rt.block_on(download_references(&options))?;
+                print!("{}", summary.render());
+                summary.check()
+            }
+            RefSubcommands::Import { archive, overwrite } => {
+                let mut options = config.import_options(archive);
+                if let Some(dir) = contents_dir {
+                    options.contents_dir = dir.clone();
+                }
+                if let Some(dir) = cache_dir("en") {
+                    options.output_dir = dir;
+                }
+                options.overwrite = overwrite.unwrap_or(options.overwrite);
+
+                let summary = import_references(&options)?;
+                print!("{}", summary.render());
+                summary.check()
+            }
+            RefSubcommands::Reprocess {
+                lang,
+                assets,
+                outdated,
+            } => {
+                let lang = lang.as_deref().unwrap_or(&config.lang);
+                let mut options = config.reprocess_options(lang);
+                if let Some(dir) = cache_dir(lang) {
+                    options.cache_dir = dir;
+                }
+                options.assets = assets.unwrap_or(options.assets);
+                options.outdated = *outdated;
+
+                let summary = reprocess_references(&options)?;
+                print!("{}", summary.render());
+                summary.check()
+            }
+            RefSubcommands::Print {
+                colored,
+                lang,
+                output,
+                order,
+                assets,
+                standard,
+            } => {
+                let lang = lang.as_deref().unwrap_or(&config.lang);
+                let colored = colored.unwrap_or(config.print.colored);
+                let mut options = config.print_options(lang, colored);
+                if let Some(dir) = contents_dir {
+                    options.contents_dir = dir.clone();
+                }
+                if let Some(dir) = cache_dir(lang) {
+                    options.input_dir = dir;
+                }
+                if let Some(output) = output {
+                    options.output_file = PathBuf::from(substitute_lang(output, lang));
+                }
+                options.standard = standard.standard.or(options.standard);
+                options.unavailable = standard.unavailable.unwrap_or(options.unavailable);
+                options.order = order.unwrap_or(options.order);
+                options.assets = assets.unwrap_or(options.assets);
 
-                rt.block_on(download_references(*overwrite, lang))
+                print_references(&options)
+            }
+            RefSubcommands::Status { lang, format, json } => {
+                let mut options = config.status_options();
+                if let Some(dir) = contents_dir {
+                    options.contents_dir = dir.clone();
+                }
+                if let Some(lang) = lang {
+                    options.caches = vec![(lang.clone(), config.cache_dir(lang))];
+                    options.include_missing = true;
+                }
+                for (lang, dir) in &mut options.caches {
+                    if let Some(override_dir) = cache_dir(lang) {
+                        *dir = override_dir;
+                    }
+                }
+                let format = if *json { OutputFormat::Json } else { *format };
+
+                let report = status_references(&options)?;
+                print!("{}", report.render(format)?);
+                Ok(())
+            }
+            RefSubcommands::Prune { lang, archive, yes } => {
+                let lang = lang.as_deref().unwrap_or(&config.lang);
+                source_for(lang)?;
+                let mut options = config.prune_options(lang);
+                if let Some(dir) = contents_dir {
+                    options.contents_dir = dir.clone();
+                }
+                if let Some(dir) = cache_dir(lang) {
+                    options.cache_dir = dir;
+                }
+                options.archive = archive.clone();
+
+                let names = orphaned_pages(&options)?;
+                if names.is_empty() {
+                    println!("No orphaned pages in {}", options.cache_dir.display());
+                    return Ok(());
+                }
+                println!(
+                    "{} orphaned page(s) in {}:",
+                    names.len(),
+                    options.cache_dir.display()
+                );
+                for name in &names {
+                    println!("  {}", name);
+                }
+                let prompt = match &options.archive {
+                    Some(dir) => format!("Move them to {}?", dir.display()),
+                    None => "Delete them?".to_string(),
+                };
+                if !*yes && !confirm(&prompt)? {
+                    println!("Nothing pruned");
+                    return Ok(());
+                }
+
+                let pruned = prune_references(&options, &names)?;
+                println!("Pruned {} page(s)", pruned);
+                Ok(())
+            }
+            RefSubcommands::List { format } => {
+                let options = ListOptions {
+                    contents_dir: contents_dir
+                        .cloned()
+                        .unwrap_or_else(|| config.contents_dir()),
+                    format: *format,
+                };
+                print!("{}", list_references(&options)?);
+                Ok(())
+            }
+            RefSubcommands::Lint { format, strict } => {
+                let options = LintOptions {
+                    contents_dir: contents_dir
+                        .cloned()
+                        .unwrap_or_else(|| config.contents_dir()),
+                    format: *format,
+                    strict: strict.unwrap_or(config.lint.strict),
+                };
+                let report = lint_references(&options)?;
+                print!("{}", report.render(options.format)?);
+                report.check(options.strict)
             }
-            RefSubcommands::Print { colored, lang } => print_references(*colored, lang),
         },
     }
 }
+
+/// Ask a yes/no question on the terminal, defaulting to no
+fn confirm(prompt: &str) -> Result<bool, AppError> {
+    eprint!("{} [y/N] ", prompt);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
@@ -7,32 +7,174 @@
 //! # Main Components
 //!
 //! - [`CppReference`] - A struct representing a C++ reference entry
+//! - [`kind`] - Kinds of references (namespace entities, macros, keywords, ...)
 //! - [`get_required_references`] - Extract all required references from Markdown files
-//! - [`compare_cpp_names`] - Compare C++ names using recursive dictionary order
+//! - [`find_reference`] - Look up a reference by its name or one of its aliases
+//! - [`compare_cpp_names`] - Compare C++ names in template-aware, natural order
+//! - [`markdown`] - Table-aware Markdown parsing used by the extractor
+//! - [`names`] - Parsing and ordering of C++ names
+//! - [`standard`] - C++ standard versions from `(C++20)` style annotations
 
-use log::debug;
-use regex::Regex;
-use std::{collections::HashMap, fs, path::Path};
+pub mod kind;
+pub mod markdown;
+pub mod names;
+pub mod standard;
+
+use log::{debug, error, warn};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
 
 use crate::{errors::AppError, utils::find_markdown_files};
 
+pub use names::compare_cpp_names;
+
+use kind::ReferenceKind;
+use markdown::{Inline, Link, parse_markdown};
+use standard::CppStandard;
+
+/// URL prefix of the cppreference.com pages that are extracted
+///
+/// Pages below `cpp/` and `c/` are extracted; see [`ReferenceKind::classify`].
+pub const CPPREFERENCE_URL_PREFIX: &str = "https://en.cppreference.com/w/";
+
+/// Header of the table column containing the C++ references
+pub const CPP_COLUMN_HEADER: &str = "C++";
+
 /// A C++ reference entry extracted from a Markdown file
 ///
 /// This struct represents a single C++ reference, containing both the
 /// function/class name (e.g., `std::vector`) and the corresponding
-/// cppreference.com URL.
-#[derive(Debug, Clone)]
+/// cppreference.com URL. A link listing several names (e.g.
+/// `` [`std::fixed`, `std::scientific`](...) ``) yields one reference whose
+/// name is the first one and whose aliases are the rest.
+///
+/// Every name records the standard it was introduced in, taken from
+/// annotations like `` `std::hexfloat` (C++11) ``, and the reference records
+/// every place in the Markdown files it was found.
+///
+/// References are keyed by [`CppReference::key`], which distinguishes e.g.
+/// the `auto` keyword from the `auto` language page.
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct CppReference {
     /// The name of the C++ function or class (e.g., `std::vector`)
     pub name: String,
+    /// The kind of the reference, derived from its URL and name
+    pub kind: ReferenceKind,
+    /// The standard the name was introduced in, if annotated
+    pub since: Option<CppStandard>,
     /// The URL to the cppreference.com page
     pub url: String,
+    /// Other names documented by the same page (e.g., `std::scientific` for `std::fixed`)
+    pub aliases: Vec<CppAlias>,
+    /// Where the reference was found, in extraction order
+    pub sources: Vec<Provenance>,
+}
+
+/// An alternative name documented by the page of a [`CppReference`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CppAlias {
+    /// The alias (e.g., `std::scientific`)
+    pub name: String,
+    /// The standard the alias was introduced in, if annotated
+    pub since: Option<CppStandard>,
+}
+
+/// The place in the Markdown files a reference was found
+///
+/// Displayed as e.g. `Iteration.md › Standard library › Functions › 去重`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Provenance {
+    /// The Markdown file
+    pub file: PathBuf,
+    /// The title of the file (its first-level heading), if any
+    pub title: Option<String>,
+    /// The headings below the title enclosing the table, outermost first
+    pub headings: Vec<String>,
+    /// The description in the first column of the row (e.g. `去重`)
+    pub row_label: String,
+    /// Line of the link (1-based)
+    pub line: usize,
+    /// Column of the link (1-based, in characters)
+    pub column: usize,
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let file_name = self
+            .file
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        write!(f, "{}", file_name)?;
+        for heading in &self.headings {
+            write!(f, " › {}", heading)?;
+        }
+        if !self.row_label.is_empty() {
+            write!(f, " › {}", self.row_label)?;
+        }
+        Ok(())
+    }
+}
+
+impl CppReference {
+    /// The key of the reference, used for lookups and cache file names
+    pub fn key(&self) -> String {
+        self.kind.key(&self.name)
+    }
+
+    /// The name followed by the kind label, if the kind has one
+    ///
+    /// For example, the C `printf` page is displayed as `printf (C)`.
+    pub fn display_name(&self) -> String {
+        match self.kind.label() {
+            Some(label) => format!("{} ({})", self.name, label),
+            None => self.name.clone(),
+        }
+    }
+
+    /// All names covered by this reference: the name followed by its aliases
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.aliases.iter().map(|a| a.name.as_str()))
+    }
+
+    /// All names with the standard they were introduced in
+    pub fn names_with_standards(&self) -> impl Iterator<Item = (&str, Option<CppStandard>)> {
+        std::iter::once((self.name.as_str(), self.since)).chain(
+            self.aliases
+                .iter()
+                .map(|alias| (alias.name.as_str(), alias.since)),
+        )
+    }
+
+    /// Whether at least one name of the page is available under `standard`
+    ///
+    /// Names without an annotation are assumed to be available everywhere.
+    pub fn is_available(&self, standard: CppStandard) -> bool {
+        self.names_with_standards()
+            .any(|(_, since)| since.is_none_or(|since| since <= standard))
+    }
+
+    /// The oldest standard any name of the page is available in, if all are annotated
+    pub fn minimum_standard(&self) -> Option<CppStandard> {
+        self.names_with_standards()
+            .map(|(_, since)| since)
+            .min()
+            .flatten()
+    }
 }
 
 /// Get all required C++ references from Markdown files
 ///
-/// This function finds all Markdown files in the `./contents` directory,
-/// extracts C++ references from them, and deduplicates them.
+/// This function finds all Markdown files in `contents_dir` (usually
+/// `./contents`), extracts C++ references from them, and deduplicates them.
+///
+/// # Arguments
+///
+/// * `contents_dir` - The directory containing the Markdown files
 ///
 /// # Returns
 ///
@@ -43,10 +185,11 @@ pub struct CppReference {
 /// Returns an error if:
 /// - The contents directory cannot be read
 /// - A Markdown file cannot be parsed
-/// - Duplicate references have conflicting URLs
-pub fn get_required_references() -> Result<HashMap<String, CppReference>, AppError> {
+/// - Duplicate references have conflicting URLs (all conflicts are reported)
+pub fn get_required_references(
+    contents_dir: &Path,
+) -> Result<HashMap<String, CppReference>, AppError> {
     // Find all markdown files in contents directory
-    let contents_dir = Path::new("./contents");
     let markdown_files = find_markdown_files(contents_dir)?;
 
     // Extract references from markdown files
@@ -60,16 +203,24 @@ pub fn get_required_references() -> Result<HashMap<String, CppReference>, AppErr
 
 /// Extract C++ references from Markdown files
 ///
-/// This function parses Markdown files to find C++ reference entries in table format.
-/// It extracts the function/class name and the corresponding cppreference.com URL.
+/// This function parses Markdown files as CommonMark with GFM tables and
+/// extracts C++ reference entries from the `C++` column of each table. Links
+/// in other columns, outside of tables, or inside code blocks are ignored, as
+/// are links that do not point to cppreference.com.
 ///
 /// # Expected Format
 ///
 /// The function looks for entries in this format:
 /// ```markdown
-/// | ... | [`std::function_name`](https://en.cppreference.com/w/cpp/...) | ... |
+/// | Functionality | C++ | Python |
+/// | ------------- | --- | ------ |
+/// | ... | [`std::function_name` (C++20)](https://en.cppreference.com/w/cpp/...) | ... |
 /// ```
 ///
+/// The first code span inside the link text is used as the name, and any
+/// further code spans are recorded as aliases. A `(C++NN)` annotation after a
+/// code span records the standard that name was introduced in.
+///
 /// # Arguments
 ///
 /// * `files` - A slice of `PathBuf` pointing to Markdown files
@@ -82,42 +233,86 @@ pub fn get_required_references() -> Result<HashMap<String, CppReference>, AppErr
 ///
 /// Returns an error if:
 /// - A file cannot be read
-/// - A reference entry has an invalid format
+/// - A link in a `C++` column has no URL
+/// - A cppreference.com link in a `C++` column has no backticked name
 pub fn extract_references(files: &[std::path::PathBuf]) -> Result<Vec<CppReference>, AppError> {
     let mut references = Vec::new();
 
-    // Regex to match individual C++ reference links in markdown tables.
-    // We intentionally match each link independently so that a single line
-    // can contain multiple links (e.g. `[...] url1 ..., [...] url2 ...`).
-    let regex = Regex::new(
-        // Capture the first `std::...` name inside a markdown link, even if the link
-        // contains extra text (e.g. `(C++20)`, aliases, etc.) before the closing `]`.
-        r#"\[`(std::[^`]+)`[^\]]*\]\((https://en\.cppreference\.com/w/cpp/[^)]+)\)"#,
-    )?;
-
     for file in files {
         let file_str = file.to_str().unwrap_or_default();
         let content = fs::read_to_string(file)?;
+        let document = parse_markdown(&content);
+
+        for table in &document.tables {
+            let title = table
+                .headings
+                .iter()
+                .find(|heading| heading.level == 1)
+                .map(|heading| heading.text.clone());
+            let headings: Vec<String> = table
+                .headings
+                .iter()
+                .filter(|heading| heading.level > 1)
+                .map(|heading| heading.text.clone())
+                .collect();
+            let Some(cpp_column) = table
+                .header
+                .iter()
+                .position(|cell| cell.text() == CPP_COLUMN_HEADER)
+            else {
+                continue;
+            };
+
+            for row in &table.rows {
+                let Some(cell) = row.cells.get(cpp_column) else {
+                    continue;
+                };
+                let row_label = row.cells.first().map(|c| c.text()).unwrap_or_default();
+
+                for link in &cell.links {
+                    if link.url.is_empty() {
+                        return Err(AppError::MissingUrl {
+                            file: file_str.to_string(),
+                            line: link.line,
+                            column: link.column,
+                        });
+                    }
 
-        for (line_num, line) in content.lines().enumerate() {
-            for captures in regex.captures_iter(line) {
-                let name = captures
-                    .get(1)
-                    .map(|m| m.as_str().trim().to_string())
-                    .ok_or_else(|| AppError::InvalidFileFormat {
-                        file: file_str.to_string(),
-                        line: line_num + 1,
-                    })?;
-
-                let url = captures
-                    .get(2)
-                    .map(|m| m.as_str().trim().to_string())
-                    .ok_or_else(|| AppError::MissingUrl {
-                        file: file_str.to_string(),
-                        line: line_num + 1,
-                    })?;
-
-                references.push(CppReference { name, url });
+                    if ReferenceKind::classify(&link.url, "").is_none() {
+                        debug!(
+                            "Ignoring non-cppreference link {} in {}:{}:{}",
+                            link.url, file_str, link.line, link.column
+                        );
+                        continue;
+                    }
+
+                    let mut names = link_names(link).into_iter();
+                    let (name, since) =
+                        names.next().ok_or_else(|| AppError::InvalidFileFormat {
+                            file: file_str.to_string(),
+                            line: link.line,
+                            column: link.column,
+                        })?;
+                    let kind = ReferenceKind::classify(&link.url, &name).unwrap_or_default();
+
+                    references.push(CppReference {
+                        name,
+                        kind,
+                        since,
+                        url: link.url.clone(),
+                        aliases: names
+                            .map(|(name, since)| CppAlias { name, since })
+                            .collect(),
+                        sources: vec![Provenance {
+                            file: file.clone(),
+                            title: title.clone(),
+                            headings: headings.clone(),
+                            row_label: row_label.clone(),
+                            line: link.line,
+                            column: link.column,
+                        }],
+                    });
+                }
             }
         }
     }
@@ -125,10 +320,115 @@ pub fn extract_references(files: &[std::path::PathBuf]) -> Result<Vec<CppReferen
     Ok(references)
 }
 
+/// Collect the names in a link text with their standard annotations
+///
+/// Links to keywords and language pages without a backticked name are named
+/// by their text, e.g. `[Structured binding (C++17)](...)`.
+fn link_names(link: &Link) -> Vec<(String, Option<CppStandard>)> {
+    let mut names: Vec<(String, Option<CppStandard>)> = Vec::new();
+    for inline in &link.content {
+        match inline {
+            Inline::Code(code) => names.push((code.trim().to_string(), None)),
+            Inline::Text(text) => {
+                if let Some((_, since @ None)) = names.last_mut() {
+                    *since = CppStandard::find_annotation(text);
+                }
+            }
+        }
+    }
+
+    let plain_names_allowed =
+        ReferenceKind::classify(&link.url, "").is_some_and(|kind| kind.allows_plain_names());
+    if names.is_empty() && plain_names_allowed {
+        let text = link.text();
+        let since = CppStandard::find_annotation(&text);
+        let name = text.split("(C++").next().unwrap_or_default().trim();
+        if !name.is_empty() {
+            names.push((name.to_string(), since));
+        }
+    }
+
+    names
+}
+
+/// A name linked to two different pages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameConflict {
+    /// The conflicting name or alias
+    pub name: String,
+    /// The URL the name was first linked to
+    pub url1: String,
+    /// Where the name was first linked to `url1`
+    pub source1: Option<Provenance>,
+    /// The conflicting URL
+    pub url2: String,
+    /// Where the name was linked to `url2`
+    pub source2: Option<Provenance>,
+}
+
+impl fmt::Display for NameConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} links to {} at {} and to {} at {}",
+            self.name,
+            self.url1,
+            location(self.source1.as_ref()),
+            self.url2,
+            location(self.source2.as_ref())
+        )
+    }
+}
+
+/// The `file:line:column` location of a source, for messages
+fn location(source: Option<&Provenance>) -> String {
+    match source {
+        Some(source) => format!(
+            "{}:{}:{}",
+            source.file.display(),
+            source.line,
+            source.column
+        ),
+        None => "an unknown location".to_string(),
+    }
+}
+
+/// Normalize a URL for comparison
+///
+/// The `#fragment`, trailing slashes and a `.html` suffix are removed, since
+/// cppreference.com serves the same page with and without them.
+///
+/// # Examples
+///
+/// ```
+/// use algcmp::references::normalize_url;
+///
+/// assert_eq!(
+///     normalize_url("https://en.cppreference.com/w/cpp/algorithm/sort.html#Example"),
+///     "https://en.cppreference.com/w/cpp/algorithm/sort"
+/// );
+/// assert_eq!(
+///     normalize_url("https://en.cppreference.com/w/cpp/algorithm/sort/"),
+///     "https://en.cppreference.com/w/cpp/algorithm/sort"
+/// );
+/// ```
+pub fn normalize_url(url: &str) -> &str {
+    let url = url.split_once('#').map_or(url, |(url, _)| url);
+    let url = url.trim_end_matches('/');
+    url.strip_suffix(".html").unwrap_or(url)
+}
+
 /// Deduplicate C++ references
 ///
 /// This function removes duplicate references by name, checking for URL conflicts.
-/// If two references with the same name have different URLs, it returns an error.
+/// URLs are compared after [`normalize_url`], so links that differ only by a
+/// `.html` suffix, a trailing slash or a `#fragment` are merged with a warning.
+/// Aliases and sources of duplicate references are merged.
+///
+/// Every name or alias used for two different pages is reported, together
+/// with where both links were found, so a contents tree can be fixed in one
+/// go. Different names pointing at the same page are kept apart but produce a
+/// warning, since the page would be downloaded and printed twice.
 ///
 /// # Arguments
 ///
@@ -136,76 +436,177 @@ pub fn extract_references(files: &[std::path::PathBuf]) -> Result<Vec<CppReferen
 ///
 /// # Returns
 ///
-/// A `HashMap` mapping reference names to their `CppReference` structs.
+/// A `HashMap` mapping reference keys to their `CppReference` structs.
 ///
 /// # Errors
 ///
-/// Returns an error if duplicate references have different URLs.
+/// Returns [`AppError::DuplicateConflicts`] listing every name or alias that
+/// links to different pages.
 pub fn deduplicate_references(
     references: Vec<CppReference>,
 ) -> Result<HashMap<String, CppReference>, AppError> {
     let mut unique: HashMap<String, CppReference> = HashMap::new();
+    let mut urls: HashMap<String, (String, Option<Provenance>)> = HashMap::new();
+    let mut conflicts = Vec::new();
 
     for ref_item in references {
-        if let Some(existing) = unique.get(&ref_item.name) {
-            // Check for URL conflict
-            if existing.url != ref_item.url {
-                return Err(AppError::DuplicateConflict {
-                    name: ref_item.name.clone(),
-                    url1: existing.url.clone(),
-                    url2: ref_item.url.clone(),
-                });
+        // Check for URL conflict on every name, including aliases
+        let source = ref_item.sources.first();
+        for name in ref_item.names() {
+            let key = ref_item.kind.key(name);
+            match urls.get(&key) {
+                Some((url, first_source)) if normalize_url(url) != normalize_url(&ref_item.url) => {
+                    conflicts.push(NameConflict {
+                        name: name.to_string(),
+                        url1: url.clone(),
+                        source1: first_source.clone(),
+                        url2: ref_item.url.clone(),
+                        source2: source.cloned(),
+                    });
+                }
+                Some((url, first_source)) if *url != ref_item.url => {
+                    warn!(
+                        "{} links to {} at {} and to {} at {}, which are the same page",
+                        name,
+                        url,
+                        location(first_source.as_ref()),
+                        ref_item.url,
+                        location(source)
+                    );
+                }
+                Some(_) => {}
+                None => {
+                    urls.insert(key, (ref_item.url.clone(), source.cloned()));
+                }
             }
+        }
+
+        let key = ref_item.key();
+        if let Some(existing) = unique.get_mut(&key) {
             // Same URL, no conflict
             debug!("Duplicate entry found but no conflict: {}", ref_item.name);
+            existing.since = existing.since.or(ref_item.since);
+            for alias in ref_item.aliases {
+                match existing.aliases.iter_mut().find(|a| a.name == alias.name) {
+                    Some(known) => known.since = known.since.or(alias.since),
+                    None => existing.aliases.push(alias),
+                }
+            }
+            existing.sources.extend(ref_item.sources);
         } else {
-            unique.insert(ref_item.name.clone(), ref_item);
+            unique.insert(key, ref_item);
+        }
+    }
+
+    if !conflicts.is_empty() {
+        for conflict in &conflicts {
+            error!("Conflicting URLs: {}", conflict);
         }
+        return Err(AppError::DuplicateConflicts {
+            count: conflicts.len(),
+            conflicts,
+        });
     }
 
+    warn_about_shared_urls(&unique);
     Ok(unique)
 }
 
-/// Compare two C++ names using recursive dictionary order
+/// Warn about different references pointing at the same page
+fn warn_about_shared_urls(references: &HashMap<String, CppReference>) {
+    let mut by_url: HashMap<&str, Vec<&CppReference>> = HashMap::new();
+    for reference in references.values() {
+        by_url
+            .entry(normalize_url(&reference.url))
+            .or_default()
+            .push(reference);
+    }
+
+    let mut shared: Vec<Vec<&CppReference>> = by_url
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect();
+    for group in &mut shared {
+        group.sort_by(|a, b| compare_references(a, b));
+    }
+    shared.sort_by(|a, b| compare_references(a[0], b[0]));
+
+    for group in shared {
+        let names: Vec<String> = group
+            .iter()
+            .map(|reference| {
+                format!(
+                    "{} ({})",
+                    reference.name,
+                    location(reference.sources.first())
+                )
+            })
+            .collect();
+        warn!(
+            "Different names point at the same page {}: {}",
+            normalize_url(&group[0].url),
+            names.join(", ")
+        );
+    }
+}
+
+/// Restrict references to the names available under a standard
 ///
-/// This function splits names by `::` and compares each component
-/// using standard string dictionary order. This provides a more intuitive
-/// sorting for C++ names than simple ASCII comparison.
+/// Pages without any name available under `standard` are removed, and
+/// unavailable aliases are dropped from the remaining pages.
 ///
-/// # Examples
+/// # Arguments
 ///
-/// ```
-/// use algcmp::references::compare_cpp_names;
-/// use std::cmp::Ordering;
+/// * `references` - Deduplicated references keyed by name
+/// * `standard` - The standard supported by the judge
+pub fn restrict_to_standard(references: &mut HashMap<String, CppReference>, standard: CppStandard) {
+    references.retain(|name, reference| {
+        let available = reference.is_available(standard);
+        if !available {
+            debug!("Excluding {} (not available in {})", name, standard);
+        }
+        available
+    });
+    for reference in references.values_mut() {
+        reference
+            .aliases
+            .retain(|alias| alias.since.is_none_or(|since| since <= standard));
+    }
+}
+
+/// Find the reference covering a name
 ///
-/// assert_eq!(compare_cpp_names("std::vector", "std::list"), Ordering::Greater);
-/// assert_eq!(compare_cpp_names("std::list", "std::vector"), Ordering::Less);
-/// assert_eq!(compare_cpp_names("std::vector", "std::vector"), Ordering::Equal);
-/// assert_eq!(compare_cpp_names("std::vector", "std::vector::iterator"), Ordering::Less);
-/// ```
+/// This function looks up `name` among the reference names first, then among
+/// their aliases, so `std::scientific` resolves to the `std::fixed` page.
 ///
 /// # Arguments
 ///
-/// * `a` - First name to compare
-/// * `b` - Second name to compare
+/// * `references` - Deduplicated references keyed by name
+/// * `name` - The name or alias to look up
 ///
 /// # Returns
 ///
-/// An `Ordering` result indicating the relative order of the names.
-pub fn compare_cpp_names(a: &str, b: &str) -> std::cmp::Ordering {
-    let a_parts: Vec<&str> = a.split("::").collect();
-    let b_parts: Vec<&str> = b.split("::").collect();
-
-    // Compare each component
-    for (a_part, b_part) in a_parts.iter().zip(b_parts.iter()) {
-        match a_part.cmp(b_part) {
-            std::cmp::Ordering::Equal => continue,
-            other => return other,
-        }
-    }
+/// The reference whose name or alias is `name`, if any.
+pub fn find_reference<'a>(
+    references: &'a HashMap<String, CppReference>,
+    name: &str,
+) -> Option<&'a CppReference> {
+    references.get(name).or_else(|| {
+        references
+            .values()
+            .find(|reference| reference.names().any(|known| known == name))
+    })
+}
 
-    // If all compared components are equal, shorter name comes first
-    a_parts.len().cmp(&b_parts.len())
+/// Compare two references by kind, then by name
+///
+/// Namespace entities come first, followed by macros, keywords, language
+/// pages and C pages (see [`ReferenceKind`]). Within a kind, names are
+/// compared with [`compare_cpp_names`].
+pub fn compare_references(a: &CppReference, b: &CppReference) -> std::cmp::Ordering {
+    a.kind
+        .cmp(&b.kind)
+        .then_with(|| compare_cpp_names(&a.name, &b.name))
 }
 
 #[cfg(test)]
@@ -247,10 +648,12 @@ mod tests {
             CppReference {
                 name: "std::vector".to_string(),
                 url: "https://example.com/vector".to_string(),
+                ..Default::default()
             },
             CppReference {
                 name: "std::vector".to_string(),
                 url: "https://example.com/vector".to_string(),
+                ..Default::default()
             },
         ];
         let result = deduplicate_references(refs).unwrap();
@@ -263,25 +666,68 @@ mod tests {
             CppReference {
                 name: "std::vector".to_string(),
                 url: "https://example.com/vector1".to_string(),
+                ..Default::default()
             },
             CppReference {
                 name: "std::vector".to_string(),
                 url: "https://example.com/vector2".to_string(),
+                ..Default::default()
             },
         ];
         let result = deduplicate_references(refs);
         assert!(result.is_err());
         match result.unwrap_err() {
-            AppError::DuplicateConflict { name, .. } => {
-                assert_eq!(name, "std::vector");
+            AppError::DuplicateConflicts { count, conflicts } => {
+                assert_eq!(count, 1);
+                assert_eq!(conflicts[0].name, "std::vector");
             }
-            _ => panic!("Expected DuplicateConflict error"),
+            _ => panic!("Expected DuplicateConflicts error"),
         }
     }
 
+    #[test]
+    fn test_deduplicate_references_reports_all_conflicts() {
+        let markdown = r#"| Functionality | C++ |
+| ------------- | --- |
+| 排序 | [`std::sort`](https://en.cppreference.com/w/cpp/algorithm/sort.html) |
+| 查找 | [`std::find`](https://en.cppreference.com/w/cpp/algorithm/find.html) |
+| 排序 | [`std::sort`](https://en.cppreference.com/w/cpp/algorithm/ranges/sort.html) |
+| 查找 | [`std::find`](https://en.cppreference.com/w/cpp/algorithm/find#Example) |
+| 查找 | [`std::find_if`, `std::find`](https://en.cppreference.com/w/cpp/algorithm/find_if.html) |"#;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("test.md");
+        fs::write(&temp_file, markdown).unwrap();
+
+        let refs = extract_references(std::slice::from_ref(&temp_file)).unwrap();
+        let conflicts = match deduplicate_references(refs).unwrap_err() {
+            AppError::DuplicateConflicts { conflicts, .. } => conflicts,
+            other => panic!("Expected DuplicateConflicts error, got {:?}", other),
+        };
+
+        // The fragment on line 6 is not a conflict
+        let summary: Vec<_> = conflicts
+            .iter()
+            .map(|conflict| {
+                (
+                    conflict.name.as_str(),
+                    conflict.source1.as_ref().unwrap().line,
+                    conflict.source2.as_ref().unwrap().line,
+                )
+            })
+            .collect();
+        assert_eq!(summary, vec![("std::sort", 3, 5), ("std::find", 4, 7)]);
+        assert!(
+            conflicts[0]
+                .to_string()
+                .ends_with(&format!("ranges/sort.html at {}:5:8", temp_file.display()))
+        );
+    }
+
     #[test]
     fn test_extract_references_from_string() {
-        let markdown = r#"| Algorithm | [`std::sort`](https://en.cppreference.com/w/cpp/algorithm/sort) | Sorts elements |
+        let markdown = r#"| Category | C++ | Description |
+| -------- | --- | ----------- |
+| Algorithm | [`std::sort`](https://en.cppreference.com/w/cpp/algorithm/sort) | Sorts elements |
 | Algorithm | [`std::find`](https://en.cppreference.com/w/cpp/algorithm/find) (C++20) | Finds element |
 | Container | [`std::priority_queue`](https://en.cppreference.com/w/cpp/container/priority_queue.html), [`std::priority_queue<T,Container,Compare>::priority_queue`](https://en.cppreference.com/w/cpp/container/priority_queue/priority_queue.html) | |
 | 空视图 | [`std::ranges::views::single` (C++20), `std::ranges::single_view` (C++20)](https://en.cppreference.com/w/cpp/ranges/single_view.html) |"#;
@@ -293,15 +739,222 @@ mod tests {
 
         let refs = extract_references(&[temp_file]).unwrap();
         // We expect 5 extracted references (one per distinct markdown link).
-        // The last link contains multiple names, but only the first is considered the primary name.
+        // The last link contains multiple names: the first is the primary name,
+        // the rest are aliases.
         assert_eq!(refs.len(), 5);
         assert!(refs.iter().any(|r| r.name == "std::sort"));
         assert!(refs.iter().any(|r| r.name == "std::find"));
         assert!(refs.iter().any(|r| r.name == "std::priority_queue"));
-        assert!(refs
-            .iter()
-            .any(|r| r.name == "std::priority_queue<T,Container,Compare>::priority_queue"));
+        assert!(
+            refs.iter()
+                .any(|r| r.name == "std::priority_queue<T,Container,Compare>::priority_queue")
+        );
         assert!(refs.iter().any(|r| r.name == "std::ranges::views::single"));
         assert!(!refs.iter().any(|r| r.name == "std::ranges::single_view"));
+        let single = refs
+            .iter()
+            .find(|r| r.name == "std::ranges::views::single")
+            .unwrap();
+        assert_eq!(
+            single.aliases,
+            vec![CppAlias {
+                name: "std::ranges::single_view".to_string(),
+                since: Some(CppStandard::Cpp20),
+            }]
+        );
+        assert_eq!(single.since, Some(CppStandard::Cpp20));
+        let sort = refs.iter().find(|r| r.name == "std::sort").unwrap();
+        assert_eq!(sort.since, None);
+        assert_eq!(sort.sources.len(), 1);
+        assert_eq!(sort.sources[0].row_label, "Algorithm");
+        assert_eq!(sort.sources[0].line, 3);
+    }
+
+    #[test]
+    fn test_reference_availability() {
+        let markdown = r#"| Manipulators | C++ |
+| ------------ | --- |
+| 浮点数格式 | [`std::fixed`, `std::scientific`, `std::hexfloat` (C++11), `std::defaultfloat` (C++11)](https://en.cppreference.com/w/cpp/io/manip/fixed.html) |
+| 截断 | [`std::ranges::take_view` (C++20), `std::views::take` (C++20)](https://en.cppreference.com/w/cpp/ranges/take_view.html) |"#;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("test.md");
+        fs::write(&temp_file, markdown).unwrap();
+
+        let refs = extract_references(&[temp_file]).unwrap();
+        let standards: Vec<_> = refs[0].names_with_standards().collect();
+        assert_eq!(
+            standards,
+            vec![
+                ("std::fixed", None),
+                ("std::scientific", None),
+                ("std::hexfloat", Some(CppStandard::Cpp11)),
+                ("std::defaultfloat", Some(CppStandard::Cpp11)),
+            ]
+        );
+        assert!(refs[0].is_available(CppStandard::Cpp98));
+        assert_eq!(refs[0].minimum_standard(), None);
+        assert!(!refs[1].is_available(CppStandard::Cpp17));
+        assert!(refs[1].is_available(CppStandard::Cpp20));
+        assert_eq!(refs[1].minimum_standard(), Some(CppStandard::Cpp20));
+
+        let mut unique = deduplicate_references(refs).unwrap();
+        restrict_to_standard(&mut unique, CppStandard::Cpp03);
+        assert_eq!(unique.len(), 1);
+        assert_eq!(
+            unique["std::fixed"].names().collect::<Vec<_>>(),
+            vec!["std::fixed", "std::scientific"]
+        );
+    }
+
+    #[test]
+    fn test_deduplicate_references_aliases() {
+        let refs = vec![
+            CppReference {
+                name: "std::fixed".to_string(),
+                url: "https://example.com/fixed".to_string(),
+                aliases: vec![CppAlias {
+                    name: "std::scientific".to_string(),
+                    since: None,
+                }],
+                ..Default::default()
+            },
+            CppReference {
+                name: "std::fixed".to_string(),
+                url: "https://example.com/fixed".to_string(),
+                aliases: vec![CppAlias {
+                    name: "std::hexfloat".to_string(),
+                    since: Some(CppStandard::Cpp11),
+                }],
+                ..Default::default()
+            },
+        ];
+        let result = deduplicate_references(refs).unwrap();
+        assert_eq!(result["std::fixed"].aliases.len(), 2);
+        assert_eq!(
+            find_reference(&result, "std::hexfloat").map(|r| r.name.as_str()),
+            Some("std::fixed")
+        );
+        assert!(find_reference(&result, "std::setw").is_none());
+
+        let refs = vec![
+            CppReference {
+                name: "std::fixed".to_string(),
+                url: "https://example.com/fixed".to_string(),
+                aliases: vec![CppAlias {
+                    name: "std::scientific".to_string(),
+                    since: None,
+                }],
+                ..Default::default()
+            },
+            CppReference {
+                name: "std::scientific".to_string(),
+                url: "https://example.com/scientific".to_string(),
+                ..Default::default()
+            },
+        ];
+        match deduplicate_references(refs).unwrap_err() {
+            AppError::DuplicateConflicts { conflicts, .. } => {
+                assert_eq!(conflicts[0].name, "std::scientific")
+            }
+            other => panic!("Expected DuplicateConflicts error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_extract_references_ignores_links_outside_cpp_column() {
+        let markdown = r#"# Topic
+
+See [`std::prose`](https://en.cppreference.com/w/cpp/prose.html).
+
+| Functionality | C++ | Java |
+| ------------- | --- | ---- |
+| 排序 | [`std::sort`](https://en.cppreference.com/w/cpp/algorithm/sort.html) | [`java.util.Arrays`](https://en.cppreference.com/w/cpp/not_cpp.html) |
+
+```markdown
+| Functionality | C++ |
+| ------------- | --- |
+| 查找 | [`std::find`](https://en.cppreference.com/w/cpp/algorithm/find.html) |
+```
+"#;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("test.md");
+        fs::write(&temp_file, markdown).unwrap();
+
+        let refs = extract_references(&[temp_file]).unwrap();
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].name, "std::sort");
+        assert_eq!(refs[0].sources[0].title.as_deref(), Some("Topic"));
+        assert_eq!(refs[0].sources[0].to_string(), "test.md › 排序");
+    }
+
+    #[test]
+    fn test_extract_references_reports_error_position() {
+        let markdown = "| Functionality | C++ |\n| --- | --- |\n| 排序 | [sort](https://en.cppreference.com/w/cpp/algorithm/sort.html) |\n| 查找 | [`std::find`] |\n";
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("test.md");
+        fs::write(&temp_file, markdown).unwrap();
+
+        match extract_references(std::slice::from_ref(&temp_file)).unwrap_err() {
+            AppError::InvalidFileFormat { line, column, .. } => {
+                assert_eq!((line, column), (3, 8));
+            }
+            other => panic!("Expected InvalidFileFormat error, got {:?}", other),
+        }
+
+        fs::write(&temp_file, markdown.replace("[sort]", "[`std::sort`]")).unwrap();
+        match extract_references(&[temp_file]).unwrap_err() {
+            AppError::MissingUrl { line, column, .. } => {
+                assert_eq!((line, column), (4, 8));
+            }
+            other => panic!("Expected MissingUrl error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_extract_references_kinds() {
+        let markdown = r#"| Functionality | C++ |
+| ------------- | --- |
+| 断言 | [`assert`](https://en.cppreference.com/w/cpp/error/assert.html) |
+| 断言 | [`assert`](https://en.cppreference.com/w/c/error/assert.html) |
+| 编译期 | [`constexpr` (C++11)](https://en.cppreference.com/w/cpp/keyword/constexpr.html) |
+| 解包 | [Structured binding (C++17)](https://en.cppreference.com/w/cpp/language/structured_binding.html) |
+| 排序 | [`std::sort`](https://en.cppreference.com/w/cpp/algorithm/sort.html) |
+"#;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("test.md");
+        fs::write(&temp_file, markdown).unwrap();
+
+        let refs = extract_references(&[temp_file]).unwrap();
+        let unique = deduplicate_references(refs).unwrap();
+        let mut references: Vec<&CppReference> = unique.values().collect();
+        references.sort_by(|a, b| compare_references(a, b));
+
+        let found: Vec<_> = references
+            .iter()
+            .map(|r| (r.key(), r.kind, r.since))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (
+                    "std::sort".to_string(),
+                    ReferenceKind::NamespaceEntity,
+                    None
+                ),
+                ("assert".to_string(), ReferenceKind::Macro, None),
+                (
+                    "keyword:constexpr".to_string(),
+                    ReferenceKind::Keyword,
+                    Some(CppStandard::Cpp11)
+                ),
+                (
+                    "language:Structured binding".to_string(),
+                    ReferenceKind::LanguagePage,
+                    Some(CppStandard::Cpp17)
+                ),
+                ("c:assert".to_string(), ReferenceKind::CPage, None),
+            ]
+        );
+        assert_eq!(references[4].display_name(), "assert (C)");
     }
 }
@@ -0,0 +1,170 @@
+//! Kinds of references
+//!
+//! Besides entities in namespaces such as `std::vector` or `__gnu_pbds::tree`,
+//! the contents can link to macros (`assert`), keywords (`constexpr`),
+//! language pages (structured bindings) and pages of the C library
+//! (`printf`). The kind of a reference is derived from its URL and name and
+//! decides how it is keyed, sorted and labelled.
+
+use serde::Serialize;
+
+use super::CPPREFERENCE_URL_PREFIX;
+
+/// Unqualified lowercase names on C++ library pages that are macros
+///
+/// Other unqualified names are expected to be written in upper case
+/// (e.g. `INT_MAX`).
+pub const LOWERCASE_MACROS: [&str; 11] = [
+    "assert", "errno", "offsetof", "setjmp", "stderr", "stdin", "stdout", "va_arg", "va_copy",
+    "va_end", "va_start",
+];
+
+/// The kind of a reference, in the order kinds are sorted in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReferenceKind {
+    /// An entity in a namespace, e.g. `std::vector` (`/w/cpp/...`)
+    #[default]
+    NamespaceEntity,
+    /// A macro, e.g. `assert` or `INT_MAX` (`/w/cpp/...`)
+    Macro,
+    /// A keyword (`/w/cpp/keyword/...`)
+    Keyword,
+    /// A page about the core language (`/w/cpp/language/...`)
+    LanguagePage,
+    /// A page of the C library (`/w/c/...`)
+    CPage,
+}
+
+impl ReferenceKind {
+    /// Classify a link by its URL and name
+    ///
+    /// # Returns
+    ///
+    /// The kind, or `None` if the URL is not a C or C++ page on cppreference.com.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use algcmp::references::kind::ReferenceKind;
+    ///
+    /// let url = "https://en.cppreference.com/w/cpp/error/assert.html";
+    /// assert_eq!(ReferenceKind::classify(url, "assert"), Some(ReferenceKind::Macro));
+    /// let url = "https://en.cppreference.com/w/c/io/fprintf.html";
+    /// assert_eq!(ReferenceKind::classify(url, "printf"), Some(ReferenceKind::CPage));
+    /// assert_eq!(ReferenceKind::classify("https://example.com/", "printf"), None);
+    /// ```
+    pub fn classify(url: &str, name: &str) -> Option<ReferenceKind> {
+        let path = url.strip_prefix(CPPREFERENCE_URL_PREFIX)?;
+        if path.starts_with("c/") {
+            return Some(ReferenceKind::CPage);
+        }
+        let page = path.strip_prefix("cpp/")?;
+        if page.starts_with("keyword/") {
+            Some(ReferenceKind::Keyword)
+        } else if page.starts_with("language/") {
+            Some(ReferenceKind::LanguagePage)
+        } else if name.contains("::") {
+            Some(ReferenceKind::NamespaceEntity)
+        } else {
+            Some(ReferenceKind::Macro)
+        }
+    }
+
+    /// Whether links of this kind may use their text as the name
+    ///
+    /// Keywords and language pages are often linked by description (e.g.
+    /// `[Structured binding](...)`), so a backticked name is not required.
+    pub fn allows_plain_names(self) -> bool {
+        matches!(self, ReferenceKind::Keyword | ReferenceKind::LanguagePage)
+    }
+
+    /// The key of a name of this kind
+    ///
+    /// Namespace entities and macros are keyed by their name. The other kinds
+    /// get a prefix, so that e.g. the `auto` keyword, the `auto` language page
+    /// and the C `assert` page do not collide with each other or with the C++
+    /// `assert` macro.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use algcmp::references::kind::ReferenceKind;
+    ///
+    /// assert_eq!(ReferenceKind::NamespaceEntity.key("std::sort"), "std::sort");
+    /// assert_eq!(ReferenceKind::CPage.key("printf"), "c:printf");
+    /// ```
+    pub fn key(self, name: &str) -> String {
+        match self.prefix() {
+            Some(prefix) => format!("{}:{}", prefix, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// A short label for names of this kind, if they need one next to the name
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            ReferenceKind::NamespaceEntity => None,
+            ReferenceKind::Macro => Some("macro"),
+            ReferenceKind::Keyword => Some("keyword"),
+            ReferenceKind::LanguagePage => Some("language"),
+            ReferenceKind::CPage => Some("C"),
+        }
+    }
+
+    /// The key prefix of this kind
+    fn prefix(self) -> Option<&'static str> {
+        match self {
+            ReferenceKind::NamespaceEntity | ReferenceKind::Macro => None,
+            ReferenceKind::Keyword => Some("keyword"),
+            ReferenceKind::LanguagePage => Some("language"),
+            ReferenceKind::CPage => Some("c"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let cases = [
+            (
+                "cpp/container/vector.html",
+                "std::vector",
+                ReferenceKind::NamespaceEntity,
+            ),
+            (
+                "cpp/ext/pb_ds/tree",
+                "__gnu_pbds::tree",
+                ReferenceKind::NamespaceEntity,
+            ),
+            ("cpp/types/climits.html", "INT_MAX", ReferenceKind::Macro),
+            (
+                "cpp/keyword/constexpr.html",
+                "constexpr",
+                ReferenceKind::Keyword,
+            ),
+            (
+                "cpp/language/structured_binding.html",
+                "Structured binding",
+                ReferenceKind::LanguagePage,
+            ),
+            ("c/algorithm/qsort.html", "qsort", ReferenceKind::CPage),
+        ];
+        for (path, name, kind) in cases {
+            let url = format!("{}{}", CPPREFERENCE_URL_PREFIX, path);
+            assert_eq!(ReferenceKind::classify(&url, name), Some(kind), "{}", url);
+        }
+        assert_eq!(
+            ReferenceKind::classify("https://en.cppreference.com/w/Main_Page", "main"),
+            None
+        );
+        assert_eq!(ReferenceKind::LanguagePage.key("auto"), "language:auto");
+        assert_ne!(
+            ReferenceKind::Keyword.key("auto"),
+            ReferenceKind::LanguagePage.key("auto")
+        );
+    }
+}
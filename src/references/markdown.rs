@@ -0,0 +1,339 @@
+//! Table-aware Markdown parsing
+//!
+//! This module reduces a CommonMark/GFM document to the tables it contains.
+//! Each table remembers the headings it appears under, and every cell, link
+//! and code span carries its line and column in the source file. Fenced code
+//! blocks, prose and other block elements are skipped.
+
+use pulldown_cmark::{CowStr, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+
+/// A parsed Markdown document, reduced to its tables
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownDocument {
+    /// All tables in document order
+    pub tables: Vec<Table>,
+}
+
+/// A GFM table
+#[derive(Debug, Clone, Default)]
+pub struct Table {
+    /// The headings enclosing the table, outermost first
+    pub headings: Vec<Heading>,
+    /// Line of the header row (1-based)
+    pub line: usize,
+    /// Cells of the header row
+    pub header: Vec<Cell>,
+    /// Body rows
+    pub rows: Vec<Row>,
+}
+
+/// A heading enclosing a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// Heading level (1 for `#`, 2 for `##`, ...)
+    pub level: u8,
+    /// Heading text
+    pub text: String,
+}
+
+/// A body row of a table
+#[derive(Debug, Clone, Default)]
+pub struct Row {
+    /// Line of the row (1-based)
+    pub line: usize,
+    /// Cells of the row
+    pub cells: Vec<Cell>,
+}
+
+/// A table cell
+#[derive(Debug, Clone, Default)]
+pub struct Cell {
+    /// Line of the cell (1-based)
+    pub line: usize,
+    /// Column of the cell (1-based, in characters)
+    pub column: usize,
+    /// Inline content outside of links
+    pub content: Vec<Inline>,
+    /// Links in the cell
+    pub links: Vec<Link>,
+}
+
+/// A Markdown link
+#[derive(Debug, Clone, Default)]
+pub struct Link {
+    /// Link destination (empty if the link has no URL)
+    pub url: String,
+    /// Line of the link (1-based)
+    pub line: usize,
+    /// Column of the link (1-based, in characters)
+    pub column: usize,
+    /// Inline content of the link text
+    pub content: Vec<Inline>,
+}
+
+/// Inline content of a cell or link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    /// A code span (the text between backticks)
+    Code(String),
+    /// Plain text
+    Text(String),
+}
+
+impl Cell {
+    /// The plain text of the cell, including link texts
+    pub fn text(&self) -> String {
+        let mut text = inline_text(&self.content);
+        for link in &self.links {
+            text.push_str(&inline_text(&link.content));
+        }
+        text.trim().to_string()
+    }
+}
+
+impl Link {
+    /// The plain text of the link, including code spans
+    pub fn text(&self) -> String {
+        inline_text(&self.content).trim().to_string()
+    }
+
+    /// The code spans in the link text
+    pub fn code_spans(&self) -> impl Iterator<Item = &str> {
+        self.content.iter().filter_map(|inline| match inline {
+            Inline::Code(code) => Some(code.as_str()),
+            Inline::Text(_) => None,
+        })
+    }
+}
+
+/// Concatenate the text of inline content
+fn inline_text(content: &[Inline]) -> String {
+    content
+        .iter()
+        .map(|inline| match inline {
+            Inline::Code(code) | Inline::Text(code) => code.as_str(),
+        })
+        .collect()
+}
+
+/// Maps byte offsets to 1-based line and column numbers
+struct LineIndex<'a> {
+    text: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(text: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { text, line_starts }
+    }
+
+    fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self.text[line_start..offset].chars().count() + 1;
+        (line, column)
+    }
+}
+
+/// Parse a Markdown document
+///
+/// Links without a URL (e.g. `` [`std::sort`] `` with no matching link
+/// definition) are reported as links with an empty URL, so callers can point
+/// at them instead of silently treating them as text.
+///
+/// # Arguments
+///
+/// * `content` - The Markdown source
+///
+/// # Returns
+///
+/// The tables of the document with their positions and enclosing headings.
+pub fn parse_markdown(content: &str) -> MarkdownDocument {
+    let index = LineIndex::new(content);
+    let mut broken_link_callback = |_| Some((CowStr::Borrowed(""), CowStr::Borrowed("")));
+    let parser = Parser::new_with_broken_link_callback(
+        content,
+        Options::ENABLE_TABLES,
+        Some(&mut broken_link_callback),
+    );
+
+    let mut document = MarkdownDocument::default();
+    let mut headings: Vec<Heading> = Vec::new();
+    let mut heading: Option<(HeadingLevel, String)> = None;
+    let mut table: Option<Table> = None;
+    let mut row: Option<Row> = None;
+    let mut cell: Option<Cell> = None;
+    let mut link: Option<Link> = None;
+
+    for (event, range) in parser.into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => heading = Some((level, String::new())),
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some((level, text)) = heading.take() {
+                    let level = level as u8;
+                    headings.retain(|outer| outer.level < level);
+                    headings.push(Heading {
+                        level,
+                        text: text.trim().to_string(),
+                    });
+                }
+            }
+            Event::Start(Tag::Table(_)) => {
+                let (line, _) = index.position(range.start);
+                table = Some(Table {
+                    headings: headings.clone(),
+                    line,
+                    ..Table::default()
+                });
+            }
+            Event::End(TagEnd::Table) => document.tables.extend(table.take()),
+            Event::Start(Tag::TableHead) | Event::Start(Tag::TableRow) => {
+                let (line, _) = index.position(range.start);
+                row = Some(Row {
+                    line,
+                    cells: Vec::new(),
+                });
+            }
+            Event::End(TagEnd::TableHead) => {
+                if let (Some(table), Some(row)) = (table.as_mut(), row.take()) {
+                    table.header = row.cells;
+                }
+            }
+            Event::End(TagEnd::TableRow) => {
+                if let (Some(table), Some(row)) = (table.as_mut(), row.take()) {
+                    table.rows.push(row);
+                }
+            }
+            Event::Start(Tag::TableCell) => {
+                let offset = range.start + content[range.clone()].len()
+                    - content[range.clone()].trim_start().len();
+                let (line, column) = index.position(offset);
+                cell = Some(Cell {
+                    line,
+                    column,
+                    ..Cell::default()
+                });
+            }
+            Event::End(TagEnd::TableCell) => {
+                if let (Some(row), Some(cell)) = (row.as_mut(), cell.take()) {
+                    row.cells.push(cell);
+                }
+            }
+            Event::Start(Tag::Link { dest_url, .. }) if cell.is_some() => {
+                let (line, column) = index.position(range.start);
+                link = Some(Link {
+                    url: dest_url.trim().to_string(),
+                    line,
+                    column,
+                    content: Vec::new(),
+                });
+            }
+            Event::End(TagEnd::Link) => {
+                if let (Some(cell), Some(link)) = (cell.as_mut(), link.take()) {
+                    cell.links.push(link);
+                }
+            }
+            Event::Code(code) => {
+                if let Some((_, text)) = heading.as_mut() {
+                    text.push_str(&code);
+                } else if let Some(link) = link.as_mut() {
+                    link.content.push(Inline::Code(code.to_string()));
+                } else if let Some(cell) = cell.as_mut() {
+                    cell.content.push(Inline::Code(code.to_string()));
+                }
+            }
+            Event::Text(text) | Event::InlineMath(text) | Event::DisplayMath(text) => {
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(&text);
+                } else if let Some(link) = link.as_mut() {
+                    push_text(&mut link.content, &text);
+                } else if let Some(cell) = cell.as_mut() {
+                    push_text(&mut cell.content, &text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    document
+}
+
+/// Append text to inline content, merging adjacent text nodes
+fn push_text(content: &mut Vec<Inline>, text: &str) {
+    if let Some(Inline::Text(last)) = content.last_mut() {
+        last.push_str(text);
+    } else {
+        content.push(Inline::Text(text.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_markdown_tables() {
+        let markdown = r#"# Topic
+
+Prose with a [`std::ignored`](https://en.cppreference.com/w/cpp/ignored.html) link.
+
+## Standard library
+### Functions
+| Functionality | C++ |
+| ------------- | --- |
+| 排序 | [`std::sort` (C++20)](https://en.cppreference.com/w/cpp/algorithm/sort.html) |
+
+```markdown
+| Functionality | C++ |
+| ------------- | --- |
+| 查找 | [`std::find`](https://en.cppreference.com/w/cpp/algorithm/find.html) |
+```
+"#;
+        let document = parse_markdown(markdown);
+        assert_eq!(document.tables.len(), 1);
+
+        let table = &document.tables[0];
+        let headings: Vec<_> = table
+            .headings
+            .iter()
+            .map(|heading| (heading.level, heading.text.as_str()))
+            .collect();
+        assert_eq!(
+            headings,
+            vec![(1, "Topic"), (2, "Standard library"), (3, "Functions")]
+        );
+        assert_eq!(table.line, 7);
+        assert_eq!(table.header[1].text(), "C++");
+        assert_eq!(table.rows.len(), 1);
+
+        let cell = &table.rows[0].cells[1];
+        assert_eq!((cell.line, cell.column), (9, 8));
+        assert_eq!(cell.links.len(), 1);
+
+        let link = &cell.links[0];
+        assert_eq!(
+            link.url,
+            "https://en.cppreference.com/w/cpp/algorithm/sort.html"
+        );
+        assert_eq!((link.line, link.column), (9, 8));
+        assert_eq!(
+            link.content,
+            vec![
+                Inline::Code("std::sort".to_string()),
+                Inline::Text(" (C++20)".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_markdown_link_without_url() {
+        let markdown = "| C++ |\n| --- |\n| [`std::sort`] |\n";
+        let document = parse_markdown(markdown);
+        let link = &document.tables[0].rows[0].cells[0].links[0];
+        assert!(link.url.is_empty());
+        assert_eq!(link.code_spans().collect::<Vec<_>>(), vec!["std::sort"]);
+    }
+}
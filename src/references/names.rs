@@ -0,0 +1,389 @@
+//! Ordering of C++ names
+//!
+//! This module provides [`compare_cpp_names`], the order the references are
+//! listed and printed in. Names are parsed into their `::` separated
+//! components first, so that template argument lists such as
+//! `priority_queue<T,Container,Compare>` and operators such as `operator<=>`
+//! do not take part in the comparison character by character.
+
+use std::cmp::Ordering;
+
+/// Overloadable operators, in the order they are sorted in
+///
+/// Call and subscript come first, followed by the arithmetic, logical,
+/// comparison and assignment operators, and finally allocation.
+const OPERATORS: [&str; 44] = [
+    "()", "[]", "->", "->*", "*", "&", "+", "-", "!", "~", "++", "--", "/", "%", "^", "|", "<<",
+    ">>", "&&", "||", "==", "!=", "<", "<=", ">", ">=", "<=>", "=", "+=", "-=", "*=", "/=", "%=",
+    "^=", "&=", "|=", "<<=", ">>=", ",", "new", "new[]", "delete", "delete[]", "co_await",
+];
+
+/// One `::` separated component of a C++ name
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Component<'a> {
+    /// The identifier, or the whole operator (e.g. `operator[]`)
+    base: &'a str,
+    /// Position of the operator in [`OPERATORS`] (`OPERATORS.len()` for
+    /// conversion and literal operators), or `None` for identifiers
+    operator: Option<usize>,
+    /// Template arguments, if the component has a template argument list
+    arguments: Vec<&'a str>,
+    /// Anything following the template arguments, such as a parameter list
+    suffix: &'a str,
+}
+
+impl Component<'_> {
+    /// Compare the parts of two components that identify an entity
+    ///
+    /// Identifiers come before operators, which are sorted by their position
+    /// in [`OPERATORS`].
+    fn compare_base(&self, other: &Self) -> Ordering {
+        match (self.operator, other.operator) {
+            (None, None) => compare_natural(self.base, other.base),
+            (None, Some(_)) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (Some(a), Some(b)) => a
+                .cmp(&b)
+                .then_with(|| compare_natural(self.base, other.base)),
+        }
+    }
+
+    /// Compare the template arguments and suffixes of two components
+    fn compare_details(&self, other: &Self) -> Ordering {
+        let arguments = self
+            .arguments
+            .iter()
+            .zip(&other.arguments)
+            .map(|(a, b)| compare_cpp_names(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| self.arguments.len().cmp(&other.arguments.len()));
+        arguments.then_with(|| compare_natural(self.suffix, other.suffix))
+    }
+}
+
+/// Compare two C++ names
+///
+/// Names are compared component by component, so every member follows its
+/// class directly, whether or not the class is written with its template
+/// arguments (`std::vector<T,Allocator>::vector` sorts right after
+/// `std::vector`, before `std::vector_bool`). Within a scope, identifiers come
+/// before operators, and operators are sorted in a fixed order (`operator()`,
+/// `operator[]`, ..., `operator==`, ..., `operator<=>`, ...). Runs of digits
+/// are compared by their numeric value, so `std::int8_t` comes before
+/// `std::int16_t`.
+///
+/// Template arguments and parameter lists only break ties between names that
+/// are otherwise equal. Names that differ only in spelling (e.g. whitespace)
+/// are ordered by their text, so the result is `Equal` only for identical
+/// names.
+///
+/// # Examples
+///
+/// ```
+/// use algcmp::references::compare_cpp_names;
+/// use std::cmp::Ordering;
+///
+/// assert_eq!(compare_cpp_names("std::vector", "std::list"), Ordering::Greater);
+/// assert_eq!(compare_cpp_names("std::list", "std::vector"), Ordering::Less);
+/// assert_eq!(compare_cpp_names("std::vector", "std::vector"), Ordering::Equal);
+/// assert_eq!(compare_cpp_names("std::vector", "std::vector::iterator"), Ordering::Less);
+/// assert_eq!(
+///     compare_cpp_names("std::vector<T,Allocator>::operator[]", "std::vector_bool"),
+///     Ordering::Less
+/// );
+/// ```
+///
+/// # Arguments
+///
+/// * `a` - First name to compare
+/// * `b` - Second name to compare
+///
+/// # Returns
+///
+/// An `Ordering` result indicating the relative order of the names.
+pub fn compare_cpp_names(a: &str, b: &str) -> Ordering {
+    let a_components = parse_name(a);
+    let b_components = parse_name(b);
+
+    let bases = a_components
+        .iter()
+        .zip(&b_components)
+        .map(|(a, b)| a.compare_base(b))
+        .find(|ordering| ordering.is_ne())
+        // If all compared components are equal, the enclosing scope comes first
+        .unwrap_or_else(|| a_components.len().cmp(&b_components.len()));
+
+    bases
+        .then_with(|| {
+            a_components
+                .iter()
+                .zip(&b_components)
+                .map(|(a, b)| a.compare_details(b))
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        })
+        .then_with(|| a.cmp(b))
+}
+
+/// Compare two strings, treating runs of digits as numbers
+fn compare_natural(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        match (a.chars().next(), b.chars().next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x_digits, x_rest) = split_digits(a);
+                let (y_digits, y_rest) = split_digits(b);
+                let x_value = x_digits.trim_start_matches('0');
+                let y_value = y_digits.trim_start_matches('0');
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value));
+                if ordering.is_ne() {
+                    return ordering;
+                }
+                (a, b) = (x_rest, y_rest);
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                (a, b) = (&a[x.len_utf8()..], &b[y.len_utf8()..]);
+            }
+        }
+    }
+}
+
+/// Split a string after its leading run of ASCII digits
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+/// Split a name into its components
+fn parse_name(name: &str) -> Vec<Component<'_>> {
+    let mut components = Vec::new();
+    let mut rest = name;
+    loop {
+        let (component, tail) = parse_component(rest);
+        components.push(component);
+        match tail.strip_prefix("::") {
+            Some(tail) => rest = tail,
+            None => return components,
+        }
+    }
+}
+
+/// Parse the component at the start of `s`
+///
+/// # Returns
+///
+/// The component and the rest of the string, starting at the `::` after the
+/// component (or empty).
+fn parse_component(s: &str) -> (Component<'_>, &str) {
+    let (base, operator, rest) = match parse_operator(s) {
+        Some((base, operator, rest)) => (base, Some(operator), rest),
+        None => {
+            let end = s.find(['<', '(', '[', ':']).unwrap_or(s.len());
+            (s[..end].trim(), None, &s[end..])
+        }
+    };
+
+    let (arguments, rest) = match rest.strip_prefix('<') {
+        Some(inner) => {
+            let end = balanced_end(inner, '>');
+            let arguments = split_top_level(&inner[..end], ',')
+                .into_iter()
+                .map(str::trim)
+                .collect();
+            (arguments, inner.get(end + 1..).unwrap_or(""))
+        }
+        None => (Vec::new(), rest),
+    };
+
+    let end = scope_separator(rest).unwrap_or(rest.len());
+    let component = Component {
+        base,
+        operator,
+        arguments,
+        suffix: rest[..end].trim(),
+    };
+    (component, &rest[end..])
+}
+
+/// Parse an operator name such as `operator<=>` at the start of `s`
+///
+/// # Returns
+///
+/// The operator name, its position in [`OPERATORS`] and the rest of the
+/// string, or `None` if `s` does not start with an operator name.
+fn parse_operator(s: &str) -> Option<(&str, usize, &str)> {
+    let after = s.strip_prefix("operator")?;
+    if after.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        // An identifier starting with "operator", e.g. `operators`
+        return None;
+    }
+
+    let symbol = after.trim_start();
+    let skipped = after.len() - symbol.len();
+    let matched = OPERATORS
+        .iter()
+        .enumerate()
+        .filter(|(_, operator)| symbol.starts_with(*operator))
+        .max_by_key(|(_, operator)| operator.len());
+    match matched {
+        Some((position, operator)) => {
+            let end = "operator".len() + skipped + operator.len();
+            Some((&s[..end], position, &s[end..]))
+        }
+        None => {
+            // A conversion or literal operator, e.g. `operator bool`
+            let end = s.find(['<', '(', ':']).unwrap_or(s.len());
+            Some((s[..end].trim(), OPERATORS.len(), &s[end..]))
+        }
+    }
+}
+
+/// Find the byte offset of the unmatched `close` bracket in `s`
+///
+/// Returns `s.len()` if the bracket is missing.
+fn balanced_end(s: &str, close: char) -> usize {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            c if c == close && depth == 0 => return i,
+            '>' | ')' | ']' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    s.len()
+}
+
+/// Split `s` at every `separator` outside of brackets
+fn split_top_level(s: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                parts.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&s[start..]);
+    parts
+}
+
+/// Find the byte offset of the first `::` outside of brackets in `s`
+fn scope_separator(s: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth = depth.saturating_sub(1),
+            ':' if depth == 0 && s[i..].starts_with("::") => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::references::get_required_references;
+    use std::path::Path;
+
+    #[test]
+    fn test_parse_name() {
+        let components = parse_name("std::map<Key,std::less<Key>>::operator<=>");
+        let bases: Vec<_> = components.iter().map(|c| c.base).collect();
+        assert_eq!(bases, vec!["std", "map", "operator<=>"]);
+        assert_eq!(components[1].arguments, vec!["Key", "std::less<Key>"]);
+        assert_eq!(components[2].operator, Some(26));
+
+        let components = parse_name("std::vector<T,Allocator>::operator[]");
+        assert_eq!(components[2].base, "operator[]");
+        assert_eq!(components[2].suffix, "");
+    }
+
+    #[test]
+    fn test_compare_members_operators_and_numbers() {
+        let sorted = [
+            "std::int8_t",
+            "std::int16_t",
+            "std::int64_t",
+            "std::vector",
+            "std::vector<T,Allocator>::vector",
+            "std::vector<T,Allocator>::operator()",
+            "std::vector<T,Allocator>::operator[]",
+            "std::vector<T,Allocator>::operator==",
+            "std::vector<T,Allocator>::operator<",
+            "std::vector<T,Allocator>::operator<=>",
+            "std::vector<T,Allocator>::operator bool",
+            "std::vector2",
+            "std::vector10",
+            "std::vector_bool",
+        ];
+        for (i, a) in sorted.iter().enumerate() {
+            for (j, b) in sorted.iter().enumerate() {
+                assert_eq!(compare_cpp_names(a, b), i.cmp(&j), "{} vs {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_compare_names_in_contents() {
+        let contents_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("contents");
+        let references = get_required_references(&contents_dir).unwrap();
+        let mut names: Vec<&str> = references
+            .values()
+            .flat_map(|reference| reference.names())
+            .collect();
+        names.sort_by(|a, b| compare_cpp_names(a, b));
+        names.dedup();
+        assert!(names.len() > 80);
+
+        // The order is a strict total order
+        for (i, a) in names.iter().enumerate() {
+            assert_eq!(compare_cpp_names(a, a), Ordering::Equal);
+            for b in &names[i + 1..] {
+                assert_eq!(compare_cpp_names(a, b), Ordering::Less, "{} vs {}", a, b);
+                assert_eq!(compare_cpp_names(b, a), Ordering::Greater, "{} vs {}", b, a);
+            }
+        }
+
+        // Every member directly follows its class and the class's other members
+        let scope = |name: &str| -> Vec<String> {
+            parse_name(name)
+                .iter()
+                .map(|component| component.base.to_string())
+                .collect()
+        };
+        for (i, name) in names.iter().enumerate() {
+            let components = scope(name);
+            let class = &components[..components.len() - 1];
+            let Some(class_position) = names.iter().position(|other| scope(other) == class) else {
+                continue;
+            };
+            assert!(class_position < i, "{} sorts before its class", name);
+            for between in &names[class_position + 1..i] {
+                assert!(
+                    scope(between).starts_with(class),
+                    "{} sorts between {} and its class",
+                    between,
+                    name
+                );
+            }
+        }
+    }
+}
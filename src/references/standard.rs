@@ -0,0 +1,154 @@
+//! C++ standard versions
+//!
+//! This module provides the [`CppStandard`] type used for the `(C++20)` style
+//! annotations in the Markdown tables, and [`UnavailablePolicy`], which decides
+//! what happens to entries that are unavailable under the selected standard.
+
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// A C++ standard version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum CppStandard {
+    /// C++98
+    Cpp98,
+    /// C++03
+    Cpp03,
+    /// C++11
+    Cpp11,
+    /// C++14
+    Cpp14,
+    /// C++17
+    Cpp17,
+    /// C++20
+    Cpp20,
+    /// C++23
+    Cpp23,
+    /// C++26
+    Cpp26,
+}
+
+impl CppStandard {
+    /// All known standards, oldest first
+    pub const ALL: [CppStandard; 8] = [
+        CppStandard::Cpp98,
+        CppStandard::Cpp03,
+        CppStandard::Cpp11,
+        CppStandard::Cpp14,
+        CppStandard::Cpp17,
+        CppStandard::Cpp20,
+        CppStandard::Cpp23,
+        CppStandard::Cpp26,
+    ];
+
+    /// The two-digit year of the standard (e.g. `"20"` for C++20)
+    pub fn year(self) -> &'static str {
+        match self {
+            CppStandard::Cpp98 => "98",
+            CppStandard::Cpp03 => "03",
+            CppStandard::Cpp11 => "11",
+            CppStandard::Cpp14 => "14",
+            CppStandard::Cpp17 => "17",
+            CppStandard::Cpp20 => "20",
+            CppStandard::Cpp23 => "23",
+            CppStandard::Cpp26 => "26",
+        }
+    }
+
+    /// Find the first `(C++NN)` annotation in a piece of text
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use algcmp::references::standard::CppStandard;
+    ///
+    /// assert_eq!(CppStandard::find_annotation(" (C++20), "), Some(CppStandard::Cpp20));
+    /// assert_eq!(CppStandard::find_annotation(", "), None);
+    /// ```
+    pub fn find_annotation(text: &str) -> Option<CppStandard> {
+        text.match_indices("(C++").find_map(|(start, _)| {
+            let rest = &text[start + 1..];
+            let end = rest.find(')')?;
+            rest[..end].parse().ok()
+        })
+    }
+}
+
+impl fmt::Display for CppStandard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "C++{}", self.year())
+    }
+}
+
+impl FromStr for CppStandard {
+    type Err = String;
+
+    /// Parse a standard such as `C++17` or `c++17`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let year = s
+            .trim()
+            .strip_prefix("C++")
+            .or_else(|| s.trim().strip_prefix("c++"))
+            .ok_or_else(|| format!("invalid C++ standard '{}', expected e.g. c++17", s))?;
+        CppStandard::ALL
+            .into_iter()
+            .find(|standard| standard.year() == year)
+            .ok_or_else(|| format!("unknown C++ standard '{}'", s))
+    }
+}
+
+impl TryFrom<String> for CppStandard {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<CppStandard> for String {
+    fn from(standard: CppStandard) -> Self {
+        standard.to_string()
+    }
+}
+
+/// What to do with entries unavailable under the selected standard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnavailablePolicy {
+    /// Leave unavailable entries out
+    #[default]
+    Exclude,
+    /// Keep unavailable entries but mark them
+    Flag,
+}
+
+impl FromStr for UnavailablePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "exclude" => Ok(UnavailablePolicy::Exclude),
+            "flag" => Ok(UnavailablePolicy::Flag),
+            _ => Err(format!(
+                "invalid policy '{}', expected 'exclude' or 'flag'",
+                s
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_standard() {
+        assert_eq!("c++17".parse(), Ok(CppStandard::Cpp17));
+        assert_eq!("C++26".parse(), Ok(CppStandard::Cpp26));
+        assert!("c++19".parse::<CppStandard>().is_err());
+        assert!("gnu++17".parse::<CppStandard>().is_err());
+        assert!(CppStandard::Cpp03 < CppStandard::Cpp11);
+        assert_eq!(CppStandard::Cpp11.to_string(), "C++11");
+    }
+}
@@ -0,0 +1,381 @@
+//! Reference sources
+//!
+//! The Markdown files link to the English cppreference.com wiki. A
+//! [`ReferenceSource`] maps these canonical URLs to the pages of one edition
+//! and back, and knows which elements of its pages are navigation to be
+//! removed before printing.
+//!
+//! Built-in sources cover the official wiki languages (`en`, `de`, `es`, `fr`,
+//! `it`, `ja`, `pt`, `ru`) and the Chinese edition at cppreference.cn (`zh`).
+//! Use [`source_for`] to look one up by language, and [`Mirror`] to download
+//! the pages of a source from another origin.
+
+use std::fmt::Debug;
+
+use crate::errors::AppError;
+
+/// Origin of the canonical (English) pages
+pub const CANONICAL_ORIGIN: &str = "https://en.cppreference.com/";
+
+/// Selectors of the navigation elements of cppreference.com pages
+pub const NAVIGATION_SELECTORS: [&str; 2] = [".t-navbar", "#mw-head"];
+
+/// An edition of cppreference that pages can be downloaded from
+pub trait ReferenceSource: Debug + Send + Sync {
+    /// The language code selecting the source (e.g. `zh`)
+    fn lang(&self) -> &str;
+
+    /// A human-readable description of the source
+    fn description(&self) -> &str;
+
+    /// The scheme and host the pages are served from, without a trailing slash
+    /// (e.g. `https://cppreference.cn`)
+    fn origin(&self) -> String;
+
+    /// Map a canonical English URL to the URL of the same page in this source
+    ///
+    /// URLs that are not canonical cppreference.com URLs are returned unchanged.
+    fn localize_url(&self, url: &str) -> String;
+
+    /// Map a URL of this source back to the canonical English URL
+    ///
+    /// # Returns
+    ///
+    /// The canonical URL, or `None` if `url` is not a page of this source.
+    fn canonical_url(&self, url: &str) -> Option<String>;
+
+    /// Selectors of the elements removed from every downloaded page
+    ///
+    /// The first element matching each selector is removed. If a selector
+    /// matches nothing, the page is kept unchanged.
+    fn navigation_selectors(&self) -> &[&str] {
+        &NAVIGATION_SELECTORS
+    }
+}
+
+/// A cppreference wiki or mirror with the same page layout as the English wiki
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CppreferenceSite {
+    /// The language code
+    pub lang: &'static str,
+    /// A human-readable description
+    pub description: &'static str,
+    /// The host serving the pages
+    pub host: &'static str,
+    /// Whether page URLs end in `.html` like on the English wiki
+    pub html_suffix: bool,
+}
+
+impl ReferenceSource for CppreferenceSite {
+    fn lang(&self) -> &str {
+        self.lang
+    }
+
+    fn description(&self) -> &str {
+        self.description
+    }
+
+    fn origin(&self) -> String {
+        format!("https://{}", self.host)
+    }
+
+    fn localize_url(&self, url: &str) -> String {
+        let Some(path) = url.strip_prefix(CANONICAL_ORIGIN) else {
+            return url.to_string();
+        };
+        let (path, fragment) = split_fragment(path);
+        let path = if self.html_suffix {
+            path
+        } else {
+            path.strip_suffix(".html").unwrap_or(path)
+        };
+        format!("https://{}/{}{}", self.host, path, fragment)
+    }
+
+    fn canonical_url(&self, url: &str) -> Option<String> {
+        let path = url
+            .strip_prefix("https://")
+            .or_else(|| url.strip_prefix("http://"))?
+            .strip_prefix(self.host)?
+            .strip_prefix('/')?;
+        let (path, fragment) = split_fragment(path);
+        if path.ends_with(".html") || path.ends_with('/') || path.is_empty() {
+            Some(format!("{}{}{}", CANONICAL_ORIGIN, path, fragment))
+        } else {
+            Some(format!("{}{}.html{}", CANONICAL_ORIGIN, path, fragment))
+        }
+    }
+}
+
+/// A source whose pages are served from another origin
+///
+/// The URLs of the source are mapped to the same paths under the base URL,
+/// e.g. a local mirror or a test server. Everything else, including the
+/// navigation selectors, is taken from the source.
+#[derive(Debug)]
+pub struct Mirror {
+    /// The source being mirrored
+    source: &'static dyn ReferenceSource,
+    /// The origin replacing the origin of the source, without a trailing slash
+    base_url: String,
+    /// The description of the source with the base URL
+    description: String,
+}
+
+impl Mirror {
+    /// Mirror a source at a base URL
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The source whose pages are mirrored
+    /// * `base_url` - The URL replacing the origin of the source, possibly
+    ///   with a path prefix (e.g. `http://127.0.0.1:8080/zh`)
+    ///
+    /// # Errors
+    ///
+    /// Returns [`AppError::InvalidBaseUrl`] if `base_url` is not an HTTP(S) URL.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use algcmp::sources::{Mirror, ReferenceSource, source_for};
+    ///
+    /// let mirror = Mirror::new(source_for("zh").unwrap(), "http://127.0.0.1:8080/").unwrap();
+    /// let url = "https://en.cppreference.com/w/cpp/algorithm/sort.html";
+    /// assert_eq!(mirror.localize_url(url), "http://127.0.0.1:8080/w/cpp/algorithm/sort");
+    /// assert_eq!(
+    ///     mirror.canonical_url("http://127.0.0.1:8080/w/cpp/algorithm/sort").as_deref(),
+    ///     Some(url)
+    /// );
+    /// ```
+    pub fn new(source: &'static dyn ReferenceSource, base_url: &str) -> Result<Self, AppError> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        let valid = reqwest::Url::parse(base_url).is_ok_and(|url| {
+            matches!(url.scheme(), "http" | "https")
+                && url.has_host()
+                && url.query().is_none()
+                && url.fragment().is_none()
+        });
+        if !valid {
+            return Err(AppError::InvalidBaseUrl {
+                url: base_url.to_string(),
+            });
+        }
+        Ok(Mirror {
+            source,
+            base_url: base_url.to_string(),
+            description: format!("{} at {}", source.description(), base_url),
+        })
+    }
+}
+
+impl ReferenceSource for Mirror {
+    fn lang(&self) -> &str {
+        self.source.lang()
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn origin(&self) -> String {
+        self.base_url.clone()
+    }
+
+    fn localize_url(&self, url: &str) -> String {
+        let url = self.source.localize_url(url);
+        match strip_origin(&url, &self.source.origin()) {
+            Some(path) => format!("{}{}", self.base_url, path),
+            None => url,
+        }
+    }
+
+    fn canonical_url(&self, url: &str) -> Option<String> {
+        let path = strip_origin(url, &self.base_url)?;
+        self.source
+            .canonical_url(&format!("{}{}", self.source.origin(), path))
+    }
+
+    fn navigation_selectors(&self) -> &[&str] {
+        self.source.navigation_selectors()
+    }
+}
+
+/// The built-in sources
+pub const BUILTIN_SOURCES: [CppreferenceSite; 9] = [
+    CppreferenceSite {
+        lang: "en",
+        description: "English (en.cppreference.com)",
+        host: "en.cppreference.com",
+        html_suffix: true,
+    },
+    wiki("de", "German (de.cppreference.com)", "de.cppreference.com"),
+    wiki("es", "Spanish (es.cppreference.com)", "es.cppreference.com"),
+    wiki("fr", "French (fr.cppreference.com)", "fr.cppreference.com"),
+    wiki("it", "Italian (it.cppreference.com)", "it.cppreference.com"),
+    wiki(
+        "ja",
+        "Japanese (ja.cppreference.com)",
+        "ja.cppreference.com",
+    ),
+    wiki(
+        "pt",
+        "Portuguese (pt.cppreference.com)",
+        "pt.cppreference.com",
+    ),
+    wiki("ru", "Russian (ru.cppreference.com)", "ru.cppreference.com"),
+    // 中文版：cppreference.cn，URL 没有 .html 后缀
+    CppreferenceSite {
+        lang: "zh",
+        description: "Chinese (cppreference.cn)",
+        host: "cppreference.cn",
+        html_suffix: false,
+    },
+];
+
+/// A translated site of the official wiki
+///
+/// Only the English wiki uses `.html` suffixes; the translations are served
+/// by MediaWiki under plain page names.
+const fn wiki(
+    lang: &'static str,
+    description: &'static str,
+    host: &'static str,
+) -> CppreferenceSite {
+    CppreferenceSite {
+        lang,
+        description,
+        host,
+        html_suffix: false,
+    }
+}
+
+/// Look up the source of a language
+///
+/// # Errors
+///
+/// Returns [`AppError::UnknownLanguage`] if there is no source for `lang`.
+///
+/// # Examples
+///
+/// ```
+/// use algcmp::sources::source_for;
+///
+/// let source = source_for("zh").unwrap();
+/// assert_eq!(
+///     source.localize_url("https://en.cppreference.com/w/cpp/numeric/bit_floor.html"),
+///     "https://cppreference.cn/w/cpp/numeric/bit_floor"
+/// );
+/// assert!(source_for("xx").is_err());
+/// ```
+pub fn source_for(lang: &str) -> Result<&'static dyn ReferenceSource, AppError> {
+    BUILTIN_SOURCES
+        .iter()
+        .find(|source| source.lang == lang)
+        .map(|source| source as &'static dyn ReferenceSource)
+        .ok_or_else(|| AppError::UnknownLanguage {
+            lang: lang.to_string(),
+            known: languages().collect::<Vec<_>>().join(", "),
+        })
+}
+
+/// The languages of the built-in sources
+pub fn languages() -> impl Iterator<Item = &'static str> {
+    BUILTIN_SOURCES.iter().map(|source| source.lang)
+}
+
+/// The rest of a URL under an origin, starting with `/` unless empty
+fn strip_origin<'a>(url: &'a str, origin: &str) -> Option<&'a str> {
+    url.strip_prefix(origin)
+        .filter(|path| path.is_empty() || path.starts_with(['/', '?', '#']))
+}
+
+/// Split a URL path into the part before the `#fragment` and the fragment with its `#`
+fn split_fragment(path: &str) -> (&str, &str) {
+    match path.find('#') {
+        Some(index) => path.split_at(index),
+        None => (path, ""),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_localize_and_canonical_urls() {
+        let url = "https://en.cppreference.com/w/cpp/container/vector.html#Iterators";
+        let cases = [
+            ("en", url),
+            (
+                "zh",
+                "https://cppreference.cn/w/cpp/container/vector#Iterators",
+            ),
+            (
+                "ja",
+                "https://ja.cppreference.com/w/cpp/container/vector#Iterators",
+            ),
+        ];
+        for (lang, localized) in cases {
+            let source = source_for(lang).unwrap();
+            assert_eq!(source.localize_url(url), localized);
+            assert_eq!(source.canonical_url(localized).as_deref(), Some(url));
+        }
+
+        let zh = source_for("zh").unwrap();
+        assert_eq!(zh.canonical_url(url), None);
+        assert_eq!(
+            zh.localize_url("https://example.com/"),
+            "https://example.com/"
+        );
+    }
+
+    #[test]
+    fn test_unknown_language() {
+        match source_for("cn").unwrap_err() {
+            AppError::UnknownLanguage { lang, known } => {
+                assert_eq!(lang, "cn");
+                assert!(known.starts_with("en, de, "), "{}", known);
+            }
+            other => panic!("Expected UnknownLanguage, got {:?}", other),
+        }
+        assert_eq!(languages().count(), 9);
+    }
+
+    #[test]
+    fn test_mirror() {
+        let url = "https://en.cppreference.com/w/cpp/container/vector.html#Iterators";
+        let en = Mirror::new(source_for("en").unwrap(), "http://localhost:8080/mirror/").unwrap();
+        assert_eq!(en.origin(), "http://localhost:8080/mirror");
+        assert_eq!(
+            en.localize_url(url),
+            "http://localhost:8080/mirror/w/cpp/container/vector.html#Iterators"
+        );
+        assert_eq!(
+            en.canonical_url("http://localhost:8080/mirror/w/cpp/container/vector.html#Iterators")
+                .as_deref(),
+            Some(url)
+        );
+        assert_eq!(
+            en.canonical_url("http://localhost:8080/mirrored/w/cpp"),
+            None
+        );
+        assert_eq!(en.canonical_url(url), None);
+        assert_eq!(en.navigation_selectors(), NAVIGATION_SELECTORS);
+
+        for base_url in [
+            "localhost:8080",
+            "ftp://localhost/",
+            "http://localhost/?a=b",
+        ] {
+            assert!(
+                matches!(
+                    Mirror::new(source_for("en").unwrap(), base_url),
+                    Err(AppError::InvalidBaseUrl { .. })
+                ),
+                "{}",
+                base_url
+            );
+        }
+    }
+}
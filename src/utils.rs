@@ -1,17 +1,18 @@
 //! Utility functions for file system operations
 //!
 //! This module provides helper functions for finding files
-//! in directories, such as Markdown files.
+//! in directories, such as Markdown files, and for linking between them.
 
 use std::{
     fs,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
 /// Recursively find all Markdown files in a directory
 ///
 /// This function traverses a directory tree and collects all files
-/// with the `.md` extension.
+/// with the `.md` extension. The result is sorted by path, so it does not
+/// depend on the order the file system lists directory entries in.
 ///
 /// # Arguments
 ///
@@ -40,9 +41,61 @@ pub fn find_markdown_files(dir: &Path) -> Result<Vec<PathBuf>, std::io::Error> {
         }
     }
 
+    files.sort();
     Ok(files)
 }
 
+/// A relative URL from a directory to a path
+///
+/// Both paths are made absolute against the current directory first. The URL
+/// uses `/` as separator and escapes characters with a meaning in URLs, so it
+/// can be used in links of a file in `from_dir`.
+///
+/// # Arguments
+///
+/// * `from_dir` - The directory of the linking file
+/// * `to` - The path to link to
+///
+/// # Errors
+///
+/// Returns an error if the current directory cannot be determined.
+pub fn relative_url(from_dir: &Path, to: &Path) -> Result<String, std::io::Error> {
+    let from = std::path::absolute(from_dir)?;
+    let to = std::path::absolute(to)?;
+    let from: Vec<Component> = from
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let to: Vec<Component> = to
+        .components()
+        .filter(|c| *c != Component::CurDir)
+        .collect();
+    let common = from
+        .iter()
+        .zip(&to)
+        .take_while(|(from, to)| from == to)
+        .count();
+
+    let mut segments: Vec<String> = vec!["..".to_string(); from.len() - common];
+    for component in &to[common..] {
+        let segment = component.as_os_str().to_string_lossy();
+        let mut escaped = String::with_capacity(segment.len());
+        for c in segment.chars() {
+            match c {
+                ' ' | '"' | '#' | '%' | '?' | '<' | '>' => {
+                    escaped.push_str(&format!("%{:02X}", c as u32))
+                }
+                c => escaped.push(c),
+            }
+        }
+        segments.push(escaped);
+    }
+    if segments.is_empty() {
+        segments.push(".".to_string());
+    }
+    Ok(segments.join("/"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -53,4 +106,16 @@ mod tests {
         assert!(result.is_ok());
         assert!(result.unwrap().is_empty());
     }
+
+    #[test]
+    fn test_relative_url() {
+        let root = std::env::temp_dir();
+        let url = relative_url(&root.join("out"), &root.join("cppreference en/assets")).unwrap();
+        assert_eq!(url, "../cppreference%20en/assets");
+        assert_eq!(
+            relative_url(&root, &root.join("./cppreference_en/assets")).unwrap(),
+            "cppreference_en/assets"
+        );
+        assert_eq!(relative_url(&root, &root).unwrap(), ".");
+    }
 }